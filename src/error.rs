@@ -0,0 +1,52 @@
+use crate::sensors::gps::UbxError;
+
+// Crate-wide error type
+//
+// `E` is the bus error of the underlying HAL (e.g. the I2C or SPI error type), so
+// application code can match on one enum without the crate depending on a
+// concrete HAL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error<E> {
+    /// Underlying I2C bus error
+    I2c(E),
+    /// Underlying SPI bus error
+    Spi(E),
+    /// UBX frame dropped on a checksum mismatch
+    UbxChecksum,
+    /// UBX frame longer than the parser buffer
+    UbxOversize,
+    /// UBX header with a class the receiver never sends (strict sync)
+    UbxUnknownClass(u8),
+    /// Sensor did not respond or produce data in time
+    SensorTimeout,
+    /// WHO_AM_I didn't match the expected device (value read)
+    WhoAmIMismatch(u8),
+    /// Temperature read while the sensor's temperature channel is disabled
+    TemperatureDisabled,
+    /// At least one axis is at the rail of the configured full scale
+    Saturated,
+}
+
+impl<E> From<UbxError> for Error<E> {
+    fn from(error: UbxError) -> Self {
+        match error {
+            UbxError::Checksum { .. } => Error::UbxChecksum,
+            UbxError::Oversize { .. } => Error::UbxOversize,
+            UbxError::UnknownClass(class) => Error::UbxUnknownClass(class),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ubx_errors_convert() {
+        let convert = |error: UbxError| -> Error<()> { error.into() };
+        assert_eq!(convert(UbxError::Checksum { class: 0x01, id: 0x07 }), Error::UbxChecksum);
+        assert_eq!(convert(UbxError::Oversize { length: 1024 }), Error::UbxOversize);
+        assert_eq!(convert(UbxError::UnknownClass(0x42)), Error::UbxUnknownClass(0x42));
+    }
+}
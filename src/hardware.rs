@@ -1,24 +1,123 @@
+#[cfg(feature = "low-power")]
+use core::cell::RefCell;
+#[cfg(feature = "low-power")]
+use cortex_m::interrupt::Mutex;
+#[cfg(feature = "low-power")]
+use cortex_m::peripheral::{NVIC, SYST, syst::SystClkSource};
+#[cfg(feature = "low-power")]
+use cortex_m_rt::exception;
+use embedded_hal::delay::DelayNs;
+use marv::led::{LedBlinker, LedPattern};
+#[cfg(feature = "low-power")]
+use marv::queue::{ByteConsumer, ByteProducer, ByteQueue};
+use marv::sensors::gps::{UbxConfig, UbxParser};
+use marv::sensors::i2c::clock_out_stuck_slave;
 use stm32f4xx_hal::{
     pac,
     prelude::*,
     gpio::{Pin, Output, PushPull},
-    i2c::{I2c, Mode},
+    i2c::{DutyCycle, I2c, Mode},
+    rcc::{CFGR, Clocks},
+    serial::{Serial, config::Config},
+    timer::CounterMs,
 };
+#[cfg(feature = "low-power")]
+use stm32f4xx_hal::{pac::interrupt, serial::Event};
+#[cfg(feature = "watchdog")]
+use stm32f4xx_hal::watchdog::IndependentWatchdog;
 
+#[cfg(all(feature = "board-nucleo", feature = "board-custom"))]
+compile_error!("features `board-nucleo` and `board-custom` are mutually exclusive");
+#[cfg(not(any(feature = "board-nucleo", feature = "board-custom")))]
+compile_error!("enable one board feature: `board-nucleo` or `board-custom`");
+
+// Status LED: PA5 (LD2) on the Nucleo-F446RE, PC13 on the custom board
+#[cfg(feature = "board-nucleo")]
 pub type LedPin = Pin<'A', 5, Output<PushPull>>;
+#[cfg(feature = "board-custom")]
+pub type LedPin = Pin<'C', 13, Output<PushPull>>;
 pub type I2cBus = I2c<pac::I2C1>;
+pub type GpsSerial = Serial<pac::USART1>;
+
+// I2C1 clock: 100 kHz standard mode by default, 400 kHz fast mode at most
+pub const I2C_DEFAULT_HZ: u32 = 100_000;
+pub const I2C_MAX_HZ: u32 = 400_000;
+
+// Default baud rate of the NEO-M9N UART
+pub const GPS_DEFAULT_BAUD: u32 = 38_400;
+
+// Baud rate detection limits (per candidate)
+const BAUD_DETECT_TIMEOUT_US: u32 = 1_500_000;
+const BAUD_DETECT_POLL_US: u32 = 50;
+const BAUD_DETECT_MAX_BYTES: u32 = 4096;
 
 pub struct HardwareConfig {
-    pub led: LedPin,
+    pub led: StatusLed,
+    #[cfg(feature = "watchdog")]
+    pub watchdog: IndependentWatchdog,
 }
 
 pub struct Hardware {
     pub config: HardwareConfig,
     pub i2c: I2cBus,
+    pub gps_serial: GpsSerial,
+    // Only read by the low-power setup, otherwise kept for detect_baud/recover_bus
+    #[cfg_attr(not(feature = "low-power"), allow(dead_code))]
+    pub clocks: Clocks,
+    timer: CounterMs<pac::TIM2>,
+    #[cfg(feature = "low-power")]
+    gps_rx: Option<ByteConsumer<'static>>, // Filled by the USART1 interrupt
 }
 
 impl Hardware {
     pub fn new() -> Self {
+        Self::new_with_i2c_speed(I2C_DEFAULT_HZ)
+    }
+
+    /// Same as `new`, with the I2C1 clock set to `hz`
+    ///
+    /// Up to 100 kHz the bus runs in standard mode, above that in fast mode.
+    /// Fast mode needs stronger pull-ups than most breakout boards fit: at
+    /// 400 kHz the rise time limit is 300 ns, which usually means 2.2k or
+    /// lower on SCL/SDA depending on bus capacitance.
+    ///
+    /// Panics if `hz` is 0 or above I2C_MAX_HZ.
+    pub fn new_with_i2c_speed(hz: u32) -> Self {
+        Self::new_with_config(hz, Config::default().baudrate(GPS_DEFAULT_BAUD.bps()), |cfgr| cfgr)
+    }
+
+    /// Same as `new`, with every board setting given explicitly
+    ///
+    /// `i2c_hz` is as for `new_with_i2c_speed`. `serial_config` sets the
+    /// USART1 framing; on the STM32F4 the word length includes the parity
+    /// bit, so with parity enabled `WordLength::DataBits9` gives 8 data bits.
+    /// `configure_clocks` adjusts the RCC setup before it is frozen, e.g. to
+    /// run from the PLL. The F446 runs up to 180 MHz and the F401 up to
+    /// 84 MHz; the HAL derives the bus prescalers and flash wait states and
+    /// panics if the PLL can't produce the requested frequency. Use
+    /// `hardware.clocks` for anything set up afterwards so it sees the same
+    /// frequencies.
+    ///
+    /// The HSE is more accurate than the 16 MHz HSI (~1% over temperature),
+    /// which matters for UART baud error. On the Nucleo-F446RE it is the
+    /// ST-LINK's 8 MHz MCO output rather than a crystal, so it needs bypass
+    /// mode. 8E1 at 115200 baud with an 84 MHz core clock from it:
+    ///
+    /// ```ignore
+    /// use stm32f4xx_hal::serial::config::{Config, Parity, WordLength};
+    ///
+    /// let serial_config = Config {
+    ///     wordlength: WordLength::DataBits9,
+    ///     parity: Parity::ParityEven,
+    ///     ..Config::default().baudrate(115_200.bps())
+    /// };
+    /// let hardware = Hardware::new_with_config(I2C_DEFAULT_HZ, serial_config, |cfgr| {
+    ///     cfgr.use_hse(8.MHz()).bypass_hse_oscillator().sysclk(84.MHz())
+    /// });
+    /// ```
+    pub fn new_with_config(i2c_hz: u32, serial_config: Config, configure_clocks: impl FnOnce(CFGR) -> CFGR) -> Self {
+        assert!(i2c_hz > 0 && i2c_hz <= I2C_MAX_HZ, "I2C clock must be 1..=400000 Hz");
+
         // Get access to the device specific peripherals
         let dp = pac::Peripherals::take().unwrap();
 
@@ -27,14 +126,18 @@ impl Hardware {
         let rcc = dp.RCC.constrain();
 
         // Freeze the configuration of all the clocks in the system and store the frozen frequencies
-        let clocks = rcc.cfgr.freeze();
+        let clocks = configure_clocks(rcc.cfgr).freeze();
 
         // Acquire the GPIO peripherals
         let gpioa = dp.GPIOA.split();
         let gpiob = dp.GPIOB.split();
 
-        // Configure PA5 (built-in LED on Nucleo-F446RE) as a push-pull output
+        // Configure the status LED as a push-pull output
+        // PA5 is the built-in LED on the Nucleo-F446RE
+        #[cfg(feature = "board-nucleo")]
         let led = gpioa.pa5.into_push_pull_output();
+        #[cfg(feature = "board-custom")]
+        let led = dp.GPIOC.split().pc13.into_push_pull_output();
 
         // Configure I2C1 pins
         // PB8 = SCL (I2C1) - AF4
@@ -42,12 +145,422 @@ impl Hardware {
         let scl = gpiob.pb8.into_alternate::<4>().set_open_drain();
         let sda = gpiob.pb9.into_alternate::<4>().set_open_drain();
 
-        // Configure I2C1 with 100 kHz clock (standard mode)
-        let i2c = I2c::new(dp.I2C1, (scl, sda), Mode::standard(100.kHz()), &clocks);
+        let i2c = I2c::new(dp.I2C1, (scl, sda), i2c_mode(i2c_hz), &clocks);
+
+        // Configure USART1 for the GPS module (8N1 unless overridden)
+        // PA9 = TX (USART1) - AF7
+        // PA10 = RX (USART1) - AF7
+        let gps_serial = dp
+            .USART1
+            .serial((gpioa.pa9, gpioa.pa10), serial_config, &clocks)
+            .unwrap();
+
+        // TIM2 is 32-bit, so a 1 kHz counter runs ~49 days before wrapping
+        let mut timer = dp.TIM2.counter_ms(&clocks);
+        timer.start(u32::MAX.millis()).unwrap();
+
+        // The watchdog is only created here, it runs once start_watchdog is called
+        #[cfg(feature = "watchdog")]
+        let watchdog = IndependentWatchdog::new(dp.IWDG);
 
         Self {
-            config: HardwareConfig { led },
+            config: HardwareConfig {
+                led: StatusLed::new(led),
+                #[cfg(feature = "watchdog")]
+                watchdog,
+            },
             i2c,
+            gps_serial,
+            clocks,
+            timer,
+            #[cfg(feature = "low-power")]
+            gps_rx: None,
+        }
+    }
+
+    /// Milliseconds since hardware initialization, wrapping after ~49 days
+    /// Compare timestamps with `wrapping_sub` so the wrap is harmless
+    pub fn millis(&self) -> u32 {
+        self.timer.now().ticks()
+    }
+
+    /// Start the independent watchdog with the given timeout
+    ///
+    /// The IWDG runs from the ~32 kHz LSI through a /4../256 prescaler and a
+    /// 12-bit reload, so the timeout granularity ranges from 0.125 ms (up to
+    /// ~512 ms) to 8 ms (up to the ~32.7 s maximum). The LSI is not trimmed and
+    /// varies between parts and with temperature, so leave generous margin.
+    /// Once started the watchdog cannot be stopped until the next reset.
+    #[cfg(feature = "watchdog")]
+    pub fn start_watchdog(&mut self, timeout_ms: u32) {
+        self.config.watchdog.start(timeout_ms.millis());
+    }
+
+    /// Switch GPS reception to interrupts and start a periodic wake-up tick
+    ///
+    /// From here on the USART1 interrupt pushes received bytes into a
+    /// `ByteQueue` drained with `pop_gps_byte`, so don't read `gps_serial`
+    /// directly any more. SysTick fires every `tick_ms` so timed work (LED,
+    /// magnetometer) keeps running while no GPS data arrives. The SysTick
+    /// reload register is 24 bits, which limits `tick_ms` to ~1048 ms at the
+    /// default 16 MHz.
+    ///
+    /// `syst` comes from `cortex_m::Peripherals::take()`, which the caller
+    /// owns since the other core peripherals may be needed elsewhere.
+    ///
+    /// Panics if `tick_ms` is 0 or too long for SysTick, or if called twice,
+    /// since it takes the queue singleton.
+    #[cfg(feature = "low-power")]
+    pub fn enable_low_power(&mut self, mut syst: SYST, tick_ms: u32) {
+        assert!(tick_ms > 0, "tick interval must be at least 1 ms");
+        let reload = match (self.clocks.sysclk().raw() / 1000).checked_mul(tick_ms) {
+            Some(ticks) if ticks <= 0x0100_0000 => ticks - 1,
+            _ => panic!("tick interval too long for SysTick"),
+        };
+
+        // The queue lives in a static so both halves can be 'static; singleton!
+        // hands out the &'static mut exactly once without a `static mut`
+        let queue = cortex_m::singleton!(: ByteQueue = ByteQueue::new()).unwrap();
+        let (producer, consumer) = queue.split();
+        cortex_m::interrupt::free(|cs| {
+            GPS_IRQ_RX.borrow(cs).replace(Some(IrqRx {
+                uart: UartRx::new(),
+                producer,
+                dropped: 0,
+            }));
+        });
+        self.gps_rx = Some(consumer);
+
+        syst.set_clock_source(SystClkSource::Core);
+        syst.set_reload(reload);
+        syst.clear_current();
+        syst.enable_interrupt();
+        syst.enable_counter();
+
+        self.gps_serial.listen(Event::RxNotEmpty);
+        // SAFETY: the handler only touches GPS_IRQ_RX inside a critical section
+        unsafe { NVIC::unmask(pac::Interrupt::USART1) };
+    }
+
+    /// Next byte received by the USART1 interrupt, if any
+    /// Lock-free: the consumer half is only ever used from the main loop
+    #[cfg(feature = "low-power")]
+    pub fn pop_gps_byte(&mut self) -> Option<u8> {
+        self.gps_rx.as_mut()?.dequeue()
+    }
+
+    /// Sleep until the next interrupt unless received bytes are already waiting
+    ///
+    /// The check and the WFI run with interrupts masked. A byte arriving in
+    /// between leaves USART1 pending, which still wakes the core from WFI,
+    /// so the wake-up can't be lost; the handler runs once this returns.
+    #[cfg(feature = "low-power")]
+    pub fn sleep_until_event(&self) {
+        cortex_m::interrupt::free(|_| {
+            if !self.gps_rx.as_ref().is_some_and(|rx| rx.ready()) {
+                cortex_m::asm::wfi();
+            }
+        });
+    }
+
+    /// Receive errors cleared by the USART1 interrupt
+    #[cfg(feature = "low-power")]
+    pub fn gps_rx_errors(&self) -> u32 {
+        cortex_m::interrupt::free(|cs| {
+            GPS_IRQ_RX.borrow(cs).borrow().as_ref().map_or(0, |rx| rx.uart.error_count())
+        })
+    }
+
+    /// Bytes the USART1 interrupt dropped because the queue was full
+    #[cfg(feature = "low-power")]
+    pub fn gps_rx_dropped(&self) -> u32 {
+        cortex_m::interrupt::free(|cs| GPS_IRQ_RX.borrow(cs).borrow().as_ref().map_or(0, |rx| rx.dropped))
+    }
+
+    /// Reload the watchdog counter; call once per main loop iteration
+    /// Does nothing when the `watchdog` feature is disabled
+    pub fn feed_watchdog(&mut self) {
+        #[cfg(feature = "watchdog")]
+        self.config.watchdog.feed();
+    }
+}
+
+/// Status LED driven by wall-clock time rather than loop iterations
+///
+/// Call `update` with `Hardware::millis` as often as the main loop allows;
+/// the on/off schedule comes from `LedBlinker`, this only drives the pin.
+pub struct StatusLed {
+    pin: LedPin,
+    blinker: LedBlinker,
+    lit: bool,
+}
+
+impl StatusLed {
+    pub fn new(mut pin: LedPin) -> Self {
+        pin.set_low();
+        Self {
+            pin,
+            blinker: LedBlinker::new(),
+            lit: false,
+        }
+    }
+
+    pub fn set_pattern(&mut self, pattern: LedPattern) {
+        self.blinker.set_pattern(pattern);
+    }
+
+    /// Drive the LED according to the current pattern
+    pub fn update(&mut self, now_ms: u32) {
+        let lit = self.blinker.update(now_ms);
+        if lit != self.lit {
+            self.lit = lit;
+            if lit {
+                self.pin.set_high();
+            } else {
+                self.pin.set_low();
+            }
         }
     }
 }
+
+/// Rebuild the GPS UART at a new baud rate
+///
+/// The HAL can't change the rate of a running `Serial`, so the USART and its
+/// pins are released and a new 8N1 port is built around them.
+///
+/// When moving the GPS module to a new rate the ordering matters:
+/// 1. Send `UbxConfig::build_cfg_prt_baud` at the current rate
+/// 2. Wait for transmit-complete (`nb::block!(serial.flush())`) so the last
+///    byte has left the shift register before the USART is torn down
+/// 3. Call this function to switch the STM32 side to the new rate
+///
+/// The receiver switches as soon as it has processed the command, so its ACK
+/// is usually lost; confirm the change with a poll at the new rate instead.
+pub fn reconfigure_uart_baud(serial: GpsSerial, new_baud: u32, clocks: &Clocks) -> GpsSerial {
+    let (usart, pins) = serial.release();
+    Serial::new(usart, pins, Config::default().baudrate(new_baud.bps()), clocks).unwrap()
+}
+
+/// Find the GPS baud rate by trying each candidate in turn
+///
+/// For every candidate the USART is rebuilt at that rate, a MON-VER poll is
+/// sent, and incoming bytes are fed to a fresh parser until any frame with a
+/// valid checksum arrives (the MON-VER reply or a periodic message) or the
+/// per-candidate timeout expires.
+///
+/// Ownership: `Serial` owns both the USART1 peripheral and the PA9/PA10 pins,
+/// and the HAL can only change the baud rate by releasing them and building a
+/// new `Serial`. The port is therefore taken by value and handed back to the
+/// caller, configured at the detected rate, or at the last candidate tried if
+/// none answered.
+#[allow(dead_code)] // For GPS modules left at another rate; the demo uses the default
+pub fn detect_baud<D: DelayNs>(
+    mut serial: GpsSerial,
+    clocks: &Clocks,
+    candidates: &[u32],
+    delay: &mut D,
+) -> (GpsSerial, Option<u32>) {
+    let poll = UbxConfig::get_poll_mon_ver();
+
+    for &baud in candidates {
+        serial = reconfigure_uart_baud(serial, baud, clocks);
+
+        for &byte in poll.iter() {
+            let _ = nb::block!(serial.write(byte));
+        }
+
+        let mut parser: UbxParser = UbxParser::new();
+        let mut waited_us = 0;
+        let mut bytes_seen = 0;
+        while waited_us < BAUD_DETECT_TIMEOUT_US && bytes_seen < BAUD_DETECT_MAX_BYTES {
+            match serial.read() {
+                Ok(byte) => {
+                    bytes_seen += 1;
+                    if parser.parse_byte(byte).is_some() {
+                        return (serial, Some(baud));
+                    }
+                }
+                // Framing/noise errors are expected while at the wrong rate
+                Err(_) => {
+                    delay.delay_us(BAUD_DETECT_POLL_US);
+                    waited_us += BAUD_DETECT_POLL_US;
+                }
+            }
+        }
+    }
+
+    (serial, None)
+}
+
+// Configure I2C1 in standard or fast mode depending on the requested clock
+fn i2c_mode(i2c_hz: u32) -> Mode {
+    if i2c_hz <= I2C_DEFAULT_HZ {
+        Mode::standard(i2c_hz.Hz())
+    } else {
+        Mode::fast(i2c_hz.Hz(), DutyCycle::Ratio2to1)
+    }
+}
+
+/// Free an I2C1 bus held low by a stuck slave and reset the peripheral
+///
+/// A slave interrupted mid-read (reset, ESD) keeps driving SDA low while it
+/// waits for the rest of its byte, and every transfer after that fails. This
+/// temporarily switches PB8 (SCL) to a GPIO open-drain output and PB9 (SDA)
+/// to a GPIO input, clocks SCL until the slave lets go of SDA, sends a STOP
+/// and hands both pins back to I2C1, which is rebuilt to clear its BUSY flag.
+///
+/// `i2c_hz` should match the rate the bus was created with. Returns the new
+/// bus and whether SDA was released.
+#[allow(dead_code)] // Recovery hook for applications; the demo just reports I2C errors
+pub fn recover_bus(i2c: I2cBus, i2c_hz: u32, clocks: &Clocks) -> (I2cBus, bool) {
+    // SAFETY: the I2C1 driver owns PB8/PB9 and is consumed here, so nothing
+    // else touches these pins until they are back in alternate mode
+    let gpiob = unsafe { &*pac::GPIOB::ptr() };
+    // Half an SCL period at 100 kHz
+    let half_period = clocks.sysclk().raw() / 200_000;
+
+    // Release both lines before leaving alternate mode so SCL doesn't glitch low
+    gpiob.odr().modify(|_, w| w.odr8().high().odr9().high());
+    gpiob.moder().modify(|_, w| w.moder8().output().moder9().input());
+
+    let released = clock_out_stuck_slave(
+        || gpiob.idr().read().idr9().bit_is_set(),
+        || {
+            gpiob.odr().modify(|_, w| w.odr8().low());
+            cortex_m::asm::delay(half_period);
+            gpiob.odr().modify(|_, w| w.odr8().high());
+            cortex_m::asm::delay(half_period);
+        },
+    )
+    .is_some();
+
+    // STOP: SDA rises while SCL is high
+    gpiob.moder().modify(|_, w| w.moder9().output());
+    gpiob.odr().modify(|_, w| w.odr9().low());
+    cortex_m::asm::delay(half_period);
+    gpiob.odr().modify(|_, w| w.odr9().high());
+    cortex_m::asm::delay(half_period);
+
+    gpiob.moder().modify(|_, w| w.moder8().alternate().moder9().alternate());
+
+    // I2c::new resets I2C1 through RCC, clearing the latched BUSY flag
+    let (i2c1, pins) = i2c.release();
+    (I2c::new(i2c1, pins, i2c_mode(i2c_hz), clocks), released)
+}
+
+// Receive errors flagged in the USART status register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UartError {
+    Overrun, // ORE: a byte arrived before the previous one was read
+    Framing, // FE: stop bit missing, usually a baud mismatch or line break
+    Noise,   // NF: noise detected while sampling a bit
+    Parity,  // PE: parity mismatch (only with parity enabled)
+}
+
+/// Non-blocking GPS UART receiver that recovers from line errors
+///
+/// On the STM32F4 the ORE, FE, NF and PE flags in USART_SR are cleared by a
+/// software sequence: a read of SR followed by a read of DR. Until that runs,
+/// ORE stays set and no further bytes are received, so a single overrun at a
+/// high baud rate would wedge the receiver. `read` runs the sequence whenever
+/// one of these flags is set and reports the error; the byte read out of DR
+/// is dropped since the stream already has a gap or a corrupted byte there.
+pub struct UartRx {
+    error_count: u32,
+}
+
+impl UartRx {
+    pub const fn new() -> Self {
+        Self { error_count: 0 }
+    }
+
+    /// Read one byte, clearing and reporting any pending receive error
+    /// The serial borrow guarantees nothing else touches USART1 meanwhile
+    #[cfg(not(feature = "low-power"))] // The USART1 interrupt reads instead
+    pub fn read(&mut self, _serial: &mut GpsSerial) -> nb::Result<u8, UartError> {
+        // SAFETY: the &mut GpsSerial borrow keeps the HAL driver from
+        // accessing the USART at the same time
+        unsafe { self.read_registers() }
+    }
+
+    // Safety: the caller must have exclusive use of USART1's SR and DR
+    unsafe fn read_registers(&mut self) -> nb::Result<u8, UartError> {
+        // SAFETY: only SR and DR are read, exclusivity is up to the caller
+        let usart = unsafe { &*pac::USART1::ptr() };
+
+        // Step 1 of the clear sequence: read SR
+        let sr = usart.sr().read();
+
+        let error = if sr.ore().bit_is_set() {
+            Some(UartError::Overrun)
+        } else if sr.fe().bit_is_set() {
+            Some(UartError::Framing)
+        } else if sr.nf().bit_is_set() {
+            Some(UartError::Noise)
+        } else if sr.pe().bit_is_set() {
+            Some(UartError::Parity)
+        } else {
+            None
+        };
+
+        if let Some(error) = error {
+            // Step 2: read DR, which clears all error flags (and RXNE)
+            let _ = usart.dr().read();
+            self.error_count = self.error_count.wrapping_add(1);
+            return Err(nb::Error::Other(error));
+        }
+
+        if sr.rxne().bit_is_set() {
+            Ok(usart.dr().read().dr().bits() as u8)
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Number of receive errors cleared since creation
+    pub fn error_count(&self) -> u32 {
+        self.error_count
+    }
+}
+
+// Receive state owned by the USART1 interrupt once low-power mode is enabled
+#[cfg(feature = "low-power")]
+struct IrqRx {
+    uart: UartRx,
+    producer: ByteProducer<'static>,
+    dropped: u32, // Bytes lost to a full queue
+}
+
+#[cfg(feature = "low-power")]
+static GPS_IRQ_RX: Mutex<RefCell<Option<IrqRx>>> = Mutex::new(RefCell::new(None));
+
+#[cfg(feature = "low-power")]
+#[interrupt]
+fn USART1() {
+    cortex_m::interrupt::free(|cs| {
+        let mut rx = GPS_IRQ_RX.borrow(cs).borrow_mut();
+        let Some(rx) = rx.as_mut() else {
+            return;
+        };
+
+        // Empty the data register so RXNE is clear when the handler returns
+        loop {
+            // SAFETY: once enable_low_power has run, only this handler
+            // reads USART1 and it can't preempt itself
+            match unsafe { rx.uart.read_registers() } {
+                Ok(byte) => {
+                    if rx.producer.enqueue(byte).is_err() {
+                        rx.dropped = rx.dropped.wrapping_add(1);
+                    }
+                }
+                Err(nb::Error::Other(_)) => {} // Counted by UartRx
+                Err(nb::Error::WouldBlock) => break,
+            }
+        }
+    });
+}
+
+// Periodic wake-up in low-power mode; taking the exception is all it needs to do
+#[cfg(feature = "low-power")]
+#[exception]
+fn SysTick() {}
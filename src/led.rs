@@ -0,0 +1,112 @@
+use crate::timing::{Heartbeat, HEARTBEAT_INTERVAL_MS};
+
+// Status LED patterns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LedPattern {
+    SearchingFix, // Slow blink, 1 s period
+    GoodFix,      // Solid on
+    Error,        // Fast blink, 200 ms period
+}
+
+impl LedPattern {
+    /// Toggle interval (half the blink period), None for a solid LED
+    pub fn toggle_interval_ms(self) -> Option<u32> {
+        match self {
+            LedPattern::SearchingFix => Some(HEARTBEAT_INTERVAL_MS),
+            LedPattern::GoodFix => None,
+            LedPattern::Error => Some(100),
+        }
+    }
+}
+
+/// On/off schedule of a status LED, driven by wall-clock time rather than loop iterations
+///
+/// Call `update` with `Hardware::millis` as often as the main loop allows
+/// and drive the pin from its result; blinking patterns are timed by a
+/// `Heartbeat`, so the rate stays the same however long each iteration takes.
+/// Starts off, in `LedPattern::SearchingFix`.
+pub struct LedBlinker {
+    pattern: LedPattern,
+    heartbeat: Heartbeat,
+    lit: bool,
+}
+
+impl LedBlinker {
+    pub fn new() -> Self {
+        let pattern = LedPattern::SearchingFix;
+        Self {
+            pattern,
+            heartbeat: Heartbeat::new(pattern.toggle_interval_ms().unwrap_or(HEARTBEAT_INTERVAL_MS)),
+            lit: false,
+        }
+    }
+
+    /// Switch pattern; setting the current one again keeps its blink phase
+    pub fn set_pattern(&mut self, pattern: LedPattern) {
+        if pattern == self.pattern {
+            return;
+        }
+        self.pattern = pattern;
+        if let Some(interval) = pattern.toggle_interval_ms() {
+            self.heartbeat.set_interval(interval);
+        }
+    }
+
+    pub fn pattern(&self) -> LedPattern {
+        self.pattern
+    }
+
+    /// Advance to `now_ms` and return whether the LED should be lit
+    pub fn update(&mut self, now_ms: u32) -> bool {
+        self.lit = match self.pattern.toggle_interval_ms() {
+            Some(_) if self.heartbeat.tick(now_ms) => !self.lit,
+            Some(_) => self.lit,
+            None => true,
+        };
+        self.lit
+    }
+}
+
+impl Default for LedBlinker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // LED state at each of `times_ms`, in order
+    fn schedule(blinker: &mut LedBlinker, times_ms: &[u32]) -> Vec<bool> {
+        times_ms.iter().map(|&now| blinker.update(now)).collect()
+    }
+
+    #[test]
+    fn searching_fix_blinks_once_per_second() {
+        let mut blinker = LedBlinker::new();
+        // Toggles at 0, 500, 1000 and 1500 ms however often update runs
+        let states = schedule(&mut blinker, &[0, 1, 499, 500, 750, 999, 1000, 1499, 1500]);
+        assert_eq!(states, [true, true, true, false, false, false, true, true, false]);
+    }
+
+    #[test]
+    fn good_fix_is_solid_and_error_blinks_fast() {
+        let mut blinker = LedBlinker::new();
+        blinker.update(0);
+        blinker.update(500); // Off
+
+        blinker.set_pattern(LedPattern::GoodFix);
+        assert_eq!(schedule(&mut blinker, &[600, 5_000]), [true, true]);
+
+        // Error restarts the timing: toggle now, then every 100 ms
+        blinker.set_pattern(LedPattern::Error);
+        assert_eq!(blinker.pattern(), LedPattern::Error);
+        let states = schedule(&mut blinker, &[5_010, 5_050, 5_110, 5_150, 5_210]);
+        assert_eq!(states, [false, false, true, true, false]);
+
+        // Setting the same pattern again doesn't restart the blink
+        blinker.set_pattern(LedPattern::Error);
+        assert!(!blinker.update(5_220));
+    }
+}
@@ -0,0 +1,14 @@
+#![cfg_attr(not(test), no_std)]
+
+// Sensor drivers and the UBX protocol code, independent of the STM32 HAL so
+// they build and unit-test on the host: cargo test-host (see .cargo/config.toml).
+// The board support (clocks, pins, USART/I2C setup, interrupts) stays in the
+// firmware binary; led, queue and timing hold its pin-free logic.
+
+mod error;
+pub mod led;
+pub mod queue;
+pub mod sensors;
+pub mod timing;
+
+pub use error::Error;
@@ -2,42 +2,45 @@
 #![no_main]
 
 use cortex_m_rt::entry;
+#[cfg(not(feature = "panic-reset"))]
 use panic_halt as _;
-use rtt_target::{rprintln, rtt_init_print};
+use rtt_target::rprintln;
 
 mod hardware;
+#[cfg(feature = "panic-reset")]
+mod panic;
 
-use hardware::Hardware;
+use hardware::{Hardware, I2cBus};
+#[cfg(not(feature = "low-power"))]
+use hardware::{UartError, UartRx};
+use marv::led::LedPattern;
+use marv::sensors::gps::UbxParser;
+use marv::sensors::i2c::{I2cRegisters, ST_AUTO_INCREMENT};
+use marv::sensors::lis3mdl::{CTRL_REG1, CTRL_REG2, CTRL_REG3, CTRL_REG4, LIS3MDL_ADDRESS};
+use marv::sensors::{Lis3mdl, MagnetometerData, UbxEvent};
 
-// LIS3MDL I2C address
-const LIS3MDL_ADDR: u8 = 0x1C;
+// Watchdog timeout, comfortably above the longest main loop iteration
+#[cfg(feature = "watchdog")]
+const WATCHDOG_TIMEOUT_MS: u32 = 3000;
 
-// Register addresses
-const CTRL_REG1: u8 = 0x20;
-const CTRL_REG2: u8 = 0x21;
-const CTRL_REG3: u8 = 0x22;
-const CTRL_REG4: u8 = 0x23;
-const OUT_X_L: u8 = 0x28;
+// Interval between magnetometer readings
+const MAG_INTERVAL_MS: u32 = 1000;
+
+// SysTick wake-up interval in low-power mode, short enough for the fast LED blink
+#[cfg(feature = "low-power")]
+const LOW_POWER_TICK_MS: u32 = 100;
 
 // Simple calibration structure
 #[derive(Clone, Copy)]
 struct MagCalibration {
     offset_x: f32,
     offset_y: f32,
+    #[allow(dead_code)] // Calibrated with the others, but the heading only uses X/Y
     offset_z: f32,
     is_calibrated: bool,
 }
 
 impl MagCalibration {
-    fn new() -> Self {
-        Self {
-            offset_x: 0.0,
-            offset_y: 0.0,
-            offset_z: 0.0,
-            is_calibrated: false,
-        }
-    }
-    
     fn calculate_heading(&self, x: i16, y: i16) -> f32 {
         // Apply calibration if available
         let x_cal = if self.is_calibrated {
@@ -65,6 +68,11 @@ impl MagCalibration {
     }
 }
 
+// LIS3MDL registers, borrowing the board's I2C bus for one access
+fn mag_registers(hardware: &mut Hardware) -> I2cRegisters<&mut I2cBus> {
+    I2cRegisters::new(&mut hardware.i2c, LIS3MDL_ADDRESS, ST_AUTO_INCREMENT)
+}
+
 fn calibrate_magnetometer(hardware: &mut Hardware) -> MagCalibration {
     rprintln!("Starting magnetometer calibration...");
     rprintln!("Slowly rotate the device in all directions for 15 seconds");
@@ -78,12 +86,7 @@ fn calibrate_magnetometer(hardware: &mut Hardware) -> MagCalibration {
     
     // Calibration duration (approximately 15 seconds at ~2Hz)
     for i in 0..30 {
-        let mut buffer = [0u8; 6];
-        if let Ok(_) = hardware.i2c.write_read(LIS3MDL_ADDR, &[OUT_X_L | 0x80], &mut buffer) {
-            let x = i16::from_le_bytes([buffer[0], buffer[1]]);
-            let y = i16::from_le_bytes([buffer[2], buffer[3]]);
-            let z = i16::from_le_bytes([buffer[4], buffer[5]]);
-            
+        if let Ok(MagnetometerData { x, y, z, .. }) = Lis3mdl::with_bus(mag_registers(hardware)).read_all() {
             // Track min/max values
             if x < min_x { min_x = x; }
             if x > max_x { max_x = x; }
@@ -122,7 +125,22 @@ fn calibrate_magnetometer(hardware: &mut Hardware) -> MagCalibration {
 
 #[entry]
 fn main() -> ! {
-    rtt_init_print!();
+    #[cfg(not(feature = "defmt"))]
+    rtt_target::rtt_init_print!();
+
+    // With defmt, keep channel 0 for plain text and give defmt its own channel
+    #[cfg(feature = "defmt")]
+    {
+        let channels = rtt_target::rtt_init! {
+            up: {
+                0: { size: 1024, name: "Terminal" }
+                1: { size: 1024, name: "defmt" }
+            }
+        };
+        rtt_target::set_print_channel(channels.up.0);
+        rtt_target::set_defmt_channel(channels.up.1);
+    }
+
     rprintln!("Starting LIS3MDL magnetometer with heading calculation...");
 
     // Initialize hardware
@@ -130,10 +148,11 @@ fn main() -> ! {
     rprintln!("Hardware initialized");
 
     // Simple LIS3MDL initialization
-    let _ = hardware.i2c.write(LIS3MDL_ADDR, &[CTRL_REG1, 0x70]); // Enable X,Y axes, 80Hz
-    let _ = hardware.i2c.write(LIS3MDL_ADDR, &[CTRL_REG2, 0x00]); // ±4 gauss
-    let _ = hardware.i2c.write(LIS3MDL_ADDR, &[CTRL_REG3, 0x00]); // Continuous mode
-    let _ = hardware.i2c.write(LIS3MDL_ADDR, &[CTRL_REG4, 0x0C]); // Enable Z axis
+    let mut regs = mag_registers(&mut hardware);
+    let _ = regs.write_reg(CTRL_REG1, 0x70); // Enable X,Y axes, 80Hz
+    let _ = regs.write_reg(CTRL_REG2, 0x00); // ±4 gauss
+    let _ = regs.write_reg(CTRL_REG3, 0x00); // Continuous mode
+    let _ = regs.write_reg(CTRL_REG4, 0x0C); // Enable Z axis
     
     rprintln!("LIS3MDL initialized");
 
@@ -142,28 +161,112 @@ fn main() -> ! {
     
     rprintln!("Starting heading measurements...");
 
+    // Start after calibration, which blocks for ~15 seconds
+    #[cfg(feature = "watchdog")]
+    hardware.start_watchdog(WATCHDOG_TIMEOUT_MS);
+
+    #[cfg(feature = "low-power")]
+    run_low_power(hardware, calibration);
+    #[cfg(not(feature = "low-power"))]
+    run_polled(hardware, calibration);
+}
+
+// Feed one GPS byte to the parser and show the fix state on the LED
+fn process_gps_byte(hardware: &mut Hardware, parser: &mut UbxParser, byte: u8) {
+    if let Some(UbxEvent::NavPvt(gps_data)) = parser.parse_byte(byte) {
+        hardware.config.led.set_pattern(if gps_data.valid {
+            LedPattern::GoodFix
+        } else {
+            LedPattern::SearchingFix
+        });
+    }
+}
+
+fn print_heading(hardware: &mut Hardware, calibration: &MagCalibration) {
+    // Read raw magnetometer data
+    match Lis3mdl::with_bus(mag_registers(hardware)).read_all() {
+        Ok(MagnetometerData { x, y, z, .. }) => {
+            // Calculate calibrated heading
+            let heading = calibration.calculate_heading(x, y);
+
+            rprintln!("Raw: X={}, Y={}, Z={} | Heading: {:.1}°", x, y, z, heading);
+        }
+        Err(_) => {
+            rprintln!("Failed to read magnetometer data");
+            hardware.config.led.set_pattern(LedPattern::Error);
+        }
+    }
+}
+
+// Main loop polling the GPS UART as fast as it can spin
+#[cfg(not(feature = "low-power"))]
+fn run_polled(mut hardware: Hardware, calibration: MagCalibration) -> ! {
+    let mut uart_rx = UartRx::new();
+    let mut gps_parser: UbxParser = UbxParser::new();
+    let mut last_mag_ms = hardware.millis();
+
     loop {
-        // Read raw magnetometer data
-        let mut buffer = [0u8; 6];
-        match hardware.i2c.write_read(LIS3MDL_ADDR, &[OUT_X_L | 0x80], &mut buffer) {
-            Ok(_) => {
-                // Combine bytes (little endian)
-                let x = i16::from_le_bytes([buffer[0], buffer[1]]);
-                let y = i16::from_le_bytes([buffer[2], buffer[3]]);
-                let z = i16::from_le_bytes([buffer[4], buffer[5]]);
-                
-                // Calculate calibrated heading
-                let heading = calibration.calculate_heading(x, y);
-                
-                rprintln!("Raw: X={}, Y={}, Z={} | Heading: {:.1}°", x, y, z, heading);
-            }
-            Err(_) => {
-                rprintln!("Failed to read magnetometer data");
+        hardware.feed_watchdog();
+
+        // Drain the GPS UART; receive errors are cleared inside UartRx, so a
+        // transient overrun recovers instead of wedging RX
+        let mut last_uart_error: Option<UartError> = None;
+        loop {
+            match uart_rx.read(&mut hardware.gps_serial) {
+                Ok(byte) => process_gps_byte(&mut hardware, &mut gps_parser, byte),
+                Err(nb::Error::WouldBlock) => break,
+                Err(nb::Error::Other(e)) => last_uart_error = Some(e),
             }
         }
+        if let Some(e) = last_uart_error {
+            rprintln!("GPS UART {:?} cleared ({} total)", e, uart_rx.error_count());
+        }
+
+        let now_ms = hardware.millis();
+        hardware.config.led.update(now_ms);
+
+        if now_ms.wrapping_sub(last_mag_ms) >= MAG_INTERVAL_MS {
+            last_mag_ms = now_ms;
+            print_heading(&mut hardware, &calibration);
+        }
+    }
+}
+
+// Interrupt-driven main loop: USART1 RX and SysTick wake the core, which
+// sleeps in WFI in between instead of spinning on the UART status register
+#[cfg(feature = "low-power")]
+fn run_low_power(mut hardware: Hardware, calibration: MagCalibration) -> ! {
+    let mut gps_parser: UbxParser = UbxParser::new();
+    let core = cortex_m::Peripherals::take().unwrap();
+    hardware.enable_low_power(core.SYST, LOW_POWER_TICK_MS);
+
+    let mut last_mag_ms = hardware.millis();
+    let mut last_errors = 0;
+    let mut last_dropped = 0;
+
+    loop {
+        hardware.feed_watchdog();
+
+        while let Some(byte) = hardware.pop_gps_byte() {
+            process_gps_byte(&mut hardware, &mut gps_parser, byte);
+        }
+
+        let errors = hardware.gps_rx_errors();
+        let dropped = hardware.gps_rx_dropped();
+        if errors != last_errors || dropped != last_dropped {
+            rprintln!("GPS UART: {} errors cleared, {} bytes dropped", errors, dropped);
+            last_errors = errors;
+            last_dropped = dropped;
+        }
+
+        let now_ms = hardware.millis();
+        hardware.config.led.update(now_ms);
+
+        if now_ms.wrapping_sub(last_mag_ms) >= MAG_INTERVAL_MS {
+            last_mag_ms = now_ms;
+            print_heading(&mut hardware, &calibration);
+        }
 
-        // Toggle LED and delay
-        hardware.config.led.toggle();
-        cortex_m::asm::delay(16_000_000); // ~1 second
+        hardware.sleep_until_event();
     }
 }
@@ -0,0 +1,31 @@
+// Panic handler for unattended deployments, enabled by the `panic-reset` feature
+//
+// The default `panic_halt` handler spins forever, which keeps the faulting
+// state intact for a debugger but leaves a field unit dead until someone
+// power-cycles it. This handler instead prints the panic location and message
+// over RTT and then resets the MCU, so a logger recovers from a transient
+// fault on its own.
+//
+// Tradeoffs:
+// - The faulting state is lost; only the RTT line survives, and only if a host
+//   is attached to read it (the RTT buffer lives in RAM and is cleared on boot)
+// - A panic that repeats on every boot turns into a reset loop instead of a
+//   clean halt; pair it with a boot counter or the watchdog if that matters
+use core::panic::PanicInfo;
+
+use rtt_target::rprintln;
+
+// Busy-wait before resetting so an attached RTT host can drain the message
+// (~100 ms at the default 16 MHz HSI)
+const RESET_DELAY_CYCLES: u32 = 1_600_000;
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    cortex_m::interrupt::disable();
+
+    rprintln!("PANIC: {}", info);
+    rprintln!("Resetting...");
+
+    cortex_m::asm::delay(RESET_DELAY_CYCLES);
+    cortex_m::peripheral::SCB::sys_reset()
+}
@@ -0,0 +1,73 @@
+// Byte queue from the USART1 interrupt to the main loop's UbxParser, used in
+// low-power mode; plain heapless, so it is tested on the host
+
+use heapless::spsc::{Consumer, Producer, Queue};
+
+// ByteQueue storage size; the queue holds one byte less than this
+pub const BYTE_QUEUE_SIZE: usize = 512;
+
+pub type ByteProducer<'a> = Producer<'a, u8, BYTE_QUEUE_SIZE>;
+pub type ByteConsumer<'a> = Consumer<'a, u8, BYTE_QUEUE_SIZE>;
+
+/// Lock-free single-producer single-consumer byte queue between an ISR and the main loop
+///
+/// Wraps `heapless::spsc::Queue`, holding up to `BYTE_QUEUE_SIZE - 1` bytes.
+/// `split` hands out the two halves: the interrupt handler owns the
+/// producer and the main loop the consumer. Each half only touches its own
+/// index with atomic loads/stores, so neither side needs a critical section
+/// to push or pop, as long as each half stays in a single context (no two
+/// interrupts at different priorities pushing through one producer).
+///
+/// For the halves to be `'static` the queue itself has to be in a static.
+/// `cortex_m::singleton!` is the simplest way to get one without a
+/// `static mut`; handing the producer to the ISR still takes a one-off
+/// `Mutex<RefCell<Option<..>>>`, see `Hardware::enable_low_power`.
+pub struct ByteQueue {
+    queue: Queue<u8, BYTE_QUEUE_SIZE>,
+}
+
+impl ByteQueue {
+    pub const fn new() -> Self {
+        Self { queue: Queue::new() }
+    }
+
+    /// Split into the producer (ISR) and consumer (main loop) halves
+    pub fn split(&mut self) -> (ByteProducer<'_>, ByteConsumer<'_>) {
+        self.queue.split()
+    }
+}
+
+impl Default for ByteQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fill_and_drain_across_the_wrap() {
+        let mut queue = ByteQueue::new();
+        let (mut producer, mut consumer) = queue.split();
+
+        // Move the indices most of the way round first
+        for byte in 0..400u32 {
+            producer.enqueue(byte as u8).unwrap();
+            assert_eq!(consumer.dequeue(), Some(byte as u8));
+        }
+
+        // Fill to capacity across the end of the buffer, then overflow
+        for byte in 0..BYTE_QUEUE_SIZE - 1 {
+            producer.enqueue(byte as u8).unwrap();
+        }
+        assert_eq!(producer.enqueue(0xFF), Err(0xFF));
+
+        // Drained in order
+        for byte in 0..BYTE_QUEUE_SIZE - 1 {
+            assert_eq!(consumer.dequeue(), Some(byte as u8));
+        }
+        assert_eq!(consumer.dequeue(), None);
+    }
+}
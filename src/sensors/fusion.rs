@@ -0,0 +1,105 @@
+// GPS course only starts to count above this speed in m/s (matches COURSE_MIN_SPEED_MMS)
+pub const FUSION_MIN_SPEED_MS: f32 = 0.5;
+// Speed in m/s at which the GPS course gets the full alpha weight
+pub const FUSION_FULL_SPEED_MS: f32 = 5.0;
+
+/// Wrap an angle in degrees into 0..360
+pub fn wrap_degrees(deg: f32) -> f32 {
+    let wrapped = libm::fmodf(deg, 360.0);
+    if wrapped < 0.0 {
+        wrapped + 360.0
+    } else {
+        wrapped
+    }
+}
+
+/// Signed shortest rotation from `from` to `to` in degrees (-180..180)
+pub fn angle_difference(from: f32, to: f32) -> f32 {
+    let diff = wrap_degrees(to - from);
+    if diff > 180.0 {
+        diff - 360.0
+    } else {
+        diff
+    }
+}
+
+// Complementary filter blending magnetometer heading with GPS course over ground
+//
+// The magnetometer is responsive but drifts with local disturbances; the GPS
+// course is accurate while moving but lags and is meaningless at standstill.
+// Each update pulls the magnetometer heading towards the GPS course by
+// `alpha` scaled with a speed confidence that ramps linearly from 0 at
+// FUSION_MIN_SPEED_MS to 1 at FUSION_FULL_SPEED_MS. The blend works on the
+// shortest angular difference, so 359° and 1° average to 0°, not 180°.
+pub struct HeadingFuser {
+    alpha: f32,           // GPS weight at full speed confidence (0.0-1.0)
+    heading: Option<f32>, // Last fused heading in degrees
+}
+
+impl HeadingFuser {
+    pub fn new(alpha: f32) -> Self {
+        Self {
+            alpha: alpha.clamp(0.0, 1.0),
+            heading: None,
+        }
+    }
+
+    /// Fuse one magnetometer heading with an optional GPS course, in degrees
+    /// Returns the fused heading in 0..360
+    pub fn update(&mut self, mag_deg: f32, gps_course_deg: Option<f32>, speed_ms: f32) -> f32 {
+        let heading = match gps_course_deg {
+            Some(course) => {
+                let weight = self.alpha * Self::speed_confidence(speed_ms);
+                wrap_degrees(mag_deg + weight * angle_difference(mag_deg, course))
+            }
+            None => wrap_degrees(mag_deg),
+        };
+
+        self.heading = Some(heading);
+        heading
+    }
+
+    /// Last fused heading, None before the first update
+    pub fn heading(&self) -> Option<f32> {
+        self.heading
+    }
+
+    fn speed_confidence(speed_ms: f32) -> f32 {
+        ((speed_ms - FUSION_MIN_SPEED_MS) / (FUSION_FULL_SPEED_MS - FUSION_MIN_SPEED_MS)).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stationary_keeps_the_magnetometer_heading() {
+        let mut fuser = HeadingFuser::new(0.5);
+        assert_eq!(fuser.heading(), None);
+        assert_eq!(fuser.update(80.0, Some(200.0), 0.0), 80.0);
+        assert_eq!(fuser.update(80.0, Some(200.0), FUSION_MIN_SPEED_MS), 80.0);
+        assert_eq!(fuser.update(-90.0, None, 10.0), 270.0);
+        assert_eq!(fuser.heading(), Some(270.0));
+    }
+
+    #[test]
+    fn straight_line_motion_blends_across_north() {
+        let mut fuser = HeadingFuser::new(0.5);
+        // Full confidence: halfway along the short way round, not through 180
+        assert_eq!(fuser.update(350.0, Some(10.0), FUSION_FULL_SPEED_MS), 0.0);
+        assert_eq!(fuser.update(10.0, Some(350.0), 20.0), 0.0);
+
+        // Halfway up the speed ramp the GPS weight is halved too
+        let half_speed = (FUSION_MIN_SPEED_MS + FUSION_FULL_SPEED_MS) / 2.0;
+        assert_eq!(fuser.update(80.0, Some(120.0), half_speed), 90.0);
+    }
+
+    #[test]
+    fn angle_helpers_wrap() {
+        assert_eq!(wrap_degrees(-30.0), 330.0);
+        assert_eq!(wrap_degrees(725.0), 5.0);
+        assert_eq!(angle_difference(350.0, 10.0), 20.0);
+        assert_eq!(angle_difference(10.0, 350.0), -20.0);
+    }
+}
@@ -1,16 +1,265 @@
-#![allow(dead_code)]
+use core::cell::RefCell;
+use core::fmt;
 
-use rtt_target::rprintln;
+use critical_section::Mutex;
+use embedded_hal::delay::DelayNs;
 
 // UBX Protocol Constants
 const UBX_SYNC_CHAR_1: u8 = 0xB5;
 const UBX_SYNC_CHAR_2: u8 = 0x62;
 
-// UBX Message Classes
-const UBX_CLASS_NAV: u8 = 0x01;
+// UBX message classes the receiver can output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UbxClass {
+    Nav,         // 0x01 Navigation results
+    Rxm,         // 0x02 Receiver manager
+    Inf,         // 0x04 Information
+    Ack,         // 0x05 ACK/NAK replies
+    Cfg,         // 0x06 Configuration
+    Upd,         // 0x09 Firmware update
+    Mon,         // 0x0A Monitoring
+    Tim,         // 0x0D Timing
+    Esf,         // 0x10 External sensor fusion
+    Mga,         // 0x13 Multiple GNSS assistance
+    Log,         // 0x21 Logging
+    Sec,         // 0x27 Security
+    Hnr,         // 0x28 High rate navigation
+    Unknown(u8), // Anything else, accepted unless strict sync is on
+}
+
+impl From<u8> for UbxClass {
+    fn from(class: u8) -> Self {
+        match class {
+            0x01 => UbxClass::Nav,
+            0x02 => UbxClass::Rxm,
+            0x04 => UbxClass::Inf,
+            0x05 => UbxClass::Ack,
+            0x06 => UbxClass::Cfg,
+            0x09 => UbxClass::Upd,
+            0x0A => UbxClass::Mon,
+            0x0D => UbxClass::Tim,
+            0x10 => UbxClass::Esf,
+            0x13 => UbxClass::Mga,
+            0x21 => UbxClass::Log,
+            0x27 => UbxClass::Sec,
+            0x28 => UbxClass::Hnr,
+            other => UbxClass::Unknown(other),
+        }
+    }
+}
+
+impl From<UbxClass> for u8 {
+    fn from(class: UbxClass) -> Self {
+        match class {
+            UbxClass::Nav => 0x01,
+            UbxClass::Rxm => 0x02,
+            UbxClass::Inf => 0x04,
+            UbxClass::Ack => 0x05,
+            UbxClass::Cfg => 0x06,
+            UbxClass::Upd => 0x09,
+            UbxClass::Mon => 0x0A,
+            UbxClass::Tim => 0x0D,
+            UbxClass::Esf => 0x10,
+            UbxClass::Mga => 0x13,
+            UbxClass::Log => 0x21,
+            UbxClass::Sec => 0x27,
+            UbxClass::Hnr => 0x28,
+            UbxClass::Unknown(other) => other,
+        }
+    }
+}
+
+// UBX CFG Message IDs
+const UBX_CFG_PRT: u8 = 0x00;  // Port configuration
+const UBX_CFG_MSG: u8 = 0x01;  // Message output rate
+const UBX_CFG_RST: u8 = 0x04;  // Reset receiver / clear backup data
+const UBX_CFG_CFG: u8 = 0x09;  // Clear, save and load configurations
+const UBX_CFG_VALSET: u8 = 0x8A; // Set configuration items (M9 key/value interface)
+
+// UBX ACK Message IDs; the payload is the class and id of the acknowledged command
+const UBX_ACK_NAK: u8 = 0x00;
+const UBX_ACK_ACK: u8 = 0x01;
+const UBX_ACK_LENGTH: u16 = 2;
+
+// send_and_wait_ack limits: the timeout only advances while the line is
+// idle, so a busy line is bounded by a byte count instead
+const ACK_POLL_US: u32 = 100;
+const ACK_MAX_BYTES: u32 = 4096;
+
+// ensure_configured timeouts: a NAV-PVT poll reply or the 1 Hz output
+// should arrive within the detect window
+const UBX_DETECT_TIMEOUT_MS: u32 = 1200;
+const CONFIG_ACK_TIMEOUT_MS: u32 = 500;
+
+// CFG-VALSET layers (combine with |)
+pub const VALSET_LAYER_RAM: u8 = 0x01;
+pub const VALSET_LAYER_BBR: u8 = 0x02;
+pub const VALSET_LAYER_FLASH: u8 = 0x04;
+
+// Configuration keys
+pub const CFG_MSGOUT_UBX_NAV_PVT_UART1: u32 = 0x2091_0007; // NAV-PVT output rate on UART1 (U1)
+
+// CFG-CFG configuration section mask bits
+pub const CFG_MASK_IO_PORT: u32 = 0x0001;  // Port settings (CFG-PRT)
+pub const CFG_MASK_MSG_CONF: u32 = 0x0002; // Message rates (CFG-MSG)
+pub const CFG_MASK_INF_MSG: u32 = 0x0004;  // INF message output
+pub const CFG_MASK_NAV_CONF: u32 = 0x0008; // Navigation settings (CFG-RATE, CFG-NAV5, ...)
+pub const CFG_MASK_RXM_CONF: u32 = 0x0010; // Receiver manager settings
+
+// CFG-CFG device mask bits (where the configuration is stored)
+pub const CFG_DEVICE_BBR: u8 = 0x01;
+pub const CFG_DEVICE_FLASH: u8 = 0x02;
+pub const CFG_DEVICE_EEPROM: u8 = 0x04;
+pub const CFG_DEVICE_SPI_FLASH: u8 = 0x10;
+
+// Sections and devices used by the CFG-CFG save/load helpers
+const CFG_SAVE_SECTIONS: u32 =
+    CFG_MASK_IO_PORT | CFG_MASK_MSG_CONF | CFG_MASK_INF_MSG | CFG_MASK_NAV_CONF | CFG_MASK_RXM_CONF;
+const CFG_SAVE_DEVICES: u8 = CFG_DEVICE_BBR | CFG_DEVICE_FLASH | CFG_DEVICE_EEPROM | CFG_DEVICE_SPI_FLASH;
+
+// CFG-RST navBbrMask presets (which battery-backed data to clear)
+pub const NAV_BBR_HOT_START: u16 = 0x0000;
+pub const NAV_BBR_WARM_START: u16 = 0x0001; // Clears ephemeris
+pub const NAV_BBR_COLD_START: u16 = 0xFFFF; // Clears everything
+
+// CFG-RST reset modes
+pub const RESET_MODE_HW_WATCHDOG: u8 = 0x00; // Immediate hardware reset
+pub const RESET_MODE_SW: u8 = 0x01;          // Controlled software reset
+pub const RESET_MODE_GNSS: u8 = 0x02;        // Restart GNSS tasks only
+
+// CFG-PRT UART mode field for 8 data bits, no parity, 1 stop bit
+const UBX_UART_MODE_8N1: u32 = 0x0000_08D0;
+// UART mode field of the original hardcoded UBX-only frame, kept byte for byte
+const UBX_UART_MODE_LEGACY: u32 = 0x2300_2300;
+
+// CFG-PRT protocol mask bits for in_proto/out_proto
+pub const PROTO_UBX: u16 = 0x0001;
+pub const PROTO_NMEA: u16 = 0x0002;
+pub const PROTO_RTCM3: u16 = 0x0020;
+
+// Standard NMEA sentences, configured through CFG-MSG with class 0xF0
+const NMEA_CLASS_STD: u8 = 0xF0;
+pub const NMEA_GGA: u8 = 0x00; // Fix data: position, altitude, satellites
+pub const NMEA_GLL: u8 = 0x01; // Latitude/longitude and time
+pub const NMEA_GSA: u8 = 0x02; // DOP and active satellites
+pub const NMEA_GSV: u8 = 0x03; // Satellites in view
+pub const NMEA_RMC: u8 = 0x04; // Recommended minimum: position, speed, course, date
+pub const NMEA_VTG: u8 = 0x05; // Course and ground speed
+pub const NMEA_GRS: u8 = 0x06; // Range residuals
+pub const NMEA_GST: u8 = 0x07; // Position error statistics
+pub const NMEA_ZDA: u8 = 0x08; // Time and date
+
+// UBX NAV message IDs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum NavId {
+    Status,    // 0x03 Receiver navigation status
+    Dop,       // 0x04 Dilution of precision
+    Pvt,       // 0x07 Navigation Position Velocity Time Solution
+    TimeUtc,   // 0x21 UTC Time Solution
+    Sat,       // 0x35 Satellite information
+    Other(u8), // Any NAV message not listed above
+}
+
+impl From<u8> for NavId {
+    fn from(id: u8) -> Self {
+        match id {
+            0x03 => NavId::Status,
+            0x04 => NavId::Dop,
+            0x07 => NavId::Pvt,
+            0x21 => NavId::TimeUtc,
+            0x35 => NavId::Sat,
+            other => NavId::Other(other),
+        }
+    }
+}
+
+impl From<NavId> for u8 {
+    fn from(id: NavId) -> Self {
+        match id {
+            NavId::Status => 0x03,
+            NavId::Dop => 0x04,
+            NavId::Pvt => 0x07,
+            NavId::TimeUtc => 0x21,
+            NavId::Sat => 0x35,
+            NavId::Other(other) => other,
+        }
+    }
+}
+
+// NAV-TIMEUTC fixed payload length
+const NAV_TIMEUTC_LENGTH: u16 = 20;
+
+// CFG-PRT payload length for a UART port (the poll request is 1 byte)
+const UBX_CFG_PRT_LENGTH: u16 = 20;
+
+// NAV-PVT payload lengths needed for the fields we decode
+// (84 bytes on protocol 14, 92 bytes on later firmware)
+const NAV_PVT_MIN_LENGTH: u16 = 64;       // Core fields through gSpeed (offsets 0-63)
+const NAV_PVT_HEAD_MOT_LENGTH: u16 = 68;  // headMot (offsets 64-67)
+const NAV_PVT_ACCURACY_LENGTH: u16 = 76;  // sAcc and headAcc (offsets 68-75)
+
+// Exact payload lengths on current (M9) firmware, enforced by set_strict_length
+pub const NAV_PVT_LENGTH: u16 = 92;
+pub const NAV_DOP_LENGTH: u16 = 18;
+
+// A real 3D fix needs at least four satellites (x, y, z and clock bias);
+// a reported fix with fewer is the receiver coasting on its last solution
+const NAV_PVT_MIN_FIX_SATELLITES: u8 = 4;
+
+// Earliest year accepted as a real NAV-PVT date; before the receiver has
+// resolved time it can report placeholders like 1980 flagged as valid
+const NAV_PVT_MIN_YEAR: u16 = 2015;
+
+// Telemetry packet sizes: a fixed 32-byte fix summary, and the same with a
+// trailing CRC-16 for links that can corrupt bytes
+pub const PACKET_LEN: usize = 32;
+pub const PACKET_CRC_LEN: usize = PACKET_LEN + 2;
 
-// UBX NAV Message IDs
-const UBX_NAV_PVT: u8 = 0x07;  // Navigation Position Velocity Time Solution
+// Packet flags byte
+const PACKET_FLAG_VALID: u8 = 0x01;
+const PACKET_FLAG_COASTING: u8 = 0x02;
+const PACKET_FLAG_DEAD_RECKONED: u8 = 0x04;
+
+// Scale factors from the native integer GpsData units to SI/degrees
+pub const LAT_SCALE: f64 = 1e-7;      // latitude: 1e-7 degrees per LSB
+pub const LON_SCALE: f64 = 1e-7;      // longitude: 1e-7 degrees per LSB
+pub const ALTITUDE_SCALE: f64 = 1e-3; // height: millimeters to meters
+pub const SPEED_SCALE: f64 = 1e-3;    // speed/velocity: mm/s to m/s
+
+// Speed unit conversions from m/s; a knot is exactly 1852 m per hour
+pub const MS_TO_KNOTS: f64 = 3600.0 / 1852.0; // 1.943844...
+pub const MS_TO_KMH: f64 = 3.6;
+
+// Below this ground speed (mm/s) the velocity direction is mostly noise
+pub const COURSE_MIN_SPEED_MMS: i32 = 500;
+
+// Mean Earth radius used for great-circle distances
+const EARTH_RADIUS_M: f64 = 6_371_000.0;
+
+// Longest outage GpsData::dead_reckon will extrapolate over
+pub const DEAD_RECKON_MAX_MS: u32 = 5000;
+
+// FixQuality tiers: minimum satellites and maximum horizontal accuracy (mm)
+// for a 3D fix; anything below Fair with a fix is Poor
+pub const QUALITY_EXCELLENT_MIN_SATS: u8 = 8;
+pub const QUALITY_EXCELLENT_MAX_HACC_MM: u32 = 2_000;
+pub const QUALITY_GOOD_MIN_SATS: u8 = 6;
+pub const QUALITY_GOOD_MAX_HACC_MM: u32 = 5_000;
+pub const QUALITY_FAIR_MIN_SATS: u8 = 4;
+pub const QUALITY_FAIR_MAX_HACC_MM: u32 = 20_000;
+
+// Overall fix health, from GpsData::quality
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FixQuality {
+    NoFix,     // No position: no fix, dead reckoning only or time only
+    Poor,      // 2D fix, or 3D below the Fair thresholds
+    Fair,
+    Good,
+    Excellent,
+}
 
 // UBX Parser States
 #[derive(Clone, Copy, PartialEq)]
@@ -26,33 +275,63 @@ enum UbxParserState {
     ReadingChecksum2,
 }
 
+// Default parser payload buffer, enough for every message this crate decodes
+pub const UBX_DEFAULT_PAYLOAD_SIZE: usize = 256;
+
+// Shortest possible UBX frame: sync, class, id, length and checksum
+pub const UBX_MIN_FRAME_LEN: usize = 8;
+
 // UBX Message Structure
-struct UbxMessage {
+struct UbxMessage<const N: usize> {
     class: u8,
     id: u8,
     length: u16,
-    payload: [u8; 256], // Max UBX payload size
+    payload: [u8; N], // Payload buffer, longer frames are dropped
     checksum_a: u8,
     checksum_b: u8,
 }
 
-impl UbxMessage {
-    fn new() -> Self {
+impl<const N: usize> UbxMessage<N> {
+    const fn new() -> Self {
         Self {
             class: 0,
             id: 0,
             length: 0,
-            payload: [0; 256],
+            payload: [0; N],
             checksum_a: 0,
             checksum_b: 0,
         }
     }
 }
 
+// NAV-PVT validity flags (payload byte 11)
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ValidFlags {
+    pub date_valid: bool,     // validDate: UTC date is valid
+    pub time_valid: bool,     // validTime: UTC time of day is valid
+    pub fully_resolved: bool, // fullyResolved: no seconds uncertainty in UTC time
+    pub mag_valid: bool,      // validMag: magnetic declination is valid
+}
+
+impl ValidFlags {
+    /// Decode the NAV-PVT `valid` bit field
+    pub fn from_bits(bits: u8) -> Self {
+        Self {
+            date_valid: bits & 0x01 != 0,
+            time_valid: bits & 0x02 != 0,
+            fully_resolved: bits & 0x04 != 0,
+            mag_valid: bits & 0x08 != 0,
+        }
+    }
+}
+
 // GPS Position/Velocity/Time data from UBX-NAV-PVT
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct GpsData {
     pub valid: bool,
+    pub itow_ms: u32,        // GPS time of week of the navigation epoch in ms
     pub year: u16,
     pub month: u8,
     pub day: u8,
@@ -60,19 +339,31 @@ pub struct GpsData {
     pub minute: u8,
     pub second: u8,
     pub nano: i32,           // Nanoseconds
+    pub validity: ValidFlags, // Date/time validity flags
     pub latitude: i32,       // Latitude in 1e-7 degrees
     pub longitude: i32,      // Longitude in 1e-7 degrees
     pub height_msl: i32,     // Height above mean sea level in mm
+    pub height_ellipsoid_mm: i32, // Height above the WGS84 ellipsoid in mm
     pub horizontal_accuracy: u32, // Horizontal accuracy in mm
     pub vertical_accuracy: u32,   // Vertical accuracy in mm
     pub ground_speed: i32,   // Ground speed in mm/s
+    pub heading_motion: i32, // Heading of motion (headMot) in 1e-5 degrees, 0 if not reported
+    pub speed_accuracy_mms: u32,       // Speed accuracy estimate (sAcc) in mm/s, 0 if not reported
+    pub heading_accuracy_1e5_deg: u32, // Heading accuracy estimate (headAcc) in 1e-5 degrees, 0 if not reported
+    pub velocity_north: i32, // NED north velocity in mm/s
+    pub velocity_east: i32,  // NED east velocity in mm/s
+    pub velocity_down: i32,  // NED down velocity in mm/s
     pub satellites: u8,      // Number of satellites
+    pub fix_type: u8,        // GNSS fix type (0 = none, 2 = 2D, 3 = 3D, ...)
+    pub dead_reckoned: bool, // Position extrapolated by dead_reckon, not measured
+    pub coasting: bool,      // 3D fix reported with fewer than 4 satellites; not valid
 }
 
 impl GpsData {
     pub fn new() -> Self {
         Self {
             valid: false,
+            itow_ms: 0,
             year: 0,
             month: 0,
             day: 0,
@@ -80,32 +371,52 @@ impl GpsData {
             minute: 0,
             second: 0,
             nano: 0,
+            validity: ValidFlags::from_bits(0),
             latitude: 0,
             longitude: 0,
             height_msl: 0,
+            height_ellipsoid_mm: 0,
             horizontal_accuracy: 0,
             vertical_accuracy: 0,
             ground_speed: 0,
+            heading_motion: 0,
+            speed_accuracy_mms: 0,
+            heading_accuracy_1e5_deg: 0,
+            velocity_north: 0,
+            velocity_east: 0,
+            velocity_down: 0,
             satellites: 0,
+            fix_type: 0,
+            dead_reckoned: false,
+            coasting: false,
         }
     }
 
+    /// Print a summary of the fix over RTT
+    ///
+    /// With the `defmt` feature enabled the summary is emitted as structured
+    /// `defmt::info!` output instead of formatted `rprintln!` text:
+    ///
+    /// ```text
+    /// cargo run --features defmt
+    /// ```
+    #[cfg(not(feature = "defmt"))]
+    pub fn print_position(&self) {
+        rtt_target::rprintln!("{}", self);
+    }
+
+    /// Print a summary of the fix as structured defmt output
+    #[cfg(feature = "defmt")]
     pub fn print_position(&self) {
         if self.valid {
-            // Convert from 1e-7 degrees to degrees with 7 decimal places
-            let lat_deg = self.latitude as f64 / 1e7;
-            let lon_deg = self.longitude as f64 / 1e7;
-            let height_m = self.height_msl as f64 / 1000.0;
-            let speed_ms = self.ground_speed as f64 / 1000.0;
-            let h_acc_m = self.horizontal_accuracy as f64 / 1000.0;
-            
-            rprintln!("GPS Fix: {}/{:02}/{:02} {:02}:{:02}:{:02}", 
-                     self.year, self.month, self.day, self.hour, self.minute, self.second);
-            rprintln!("Position: {:.7}°, {:.7}° (±{:.1}m)", lat_deg, lon_deg, h_acc_m);
-            rprintln!("Altitude: {:.1}m, Speed: {:.1}m/s, Sats: {}", 
-                     height_m, speed_ms, self.satellites);
+            defmt::info!("GPS Fix: {}/{}/{} {}:{}:{}",
+                         self.year, self.month, self.day, self.hour, self.minute, self.second);
+            defmt::info!("Position: {}°, {}° (±{}m)",
+                         self.latitude_degrees(), self.longitude_degrees(), self.horizontal_accuracy_meters());
+            defmt::info!("Altitude: {}m, Speed: {}m/s, Sats: {}",
+                         self.altitude_meters(), self.speed_ms(), self.satellites);
         } else {
-            rprintln!("GPS: No valid fix");
+            defmt::info!("GPS: No valid fix");
         }
     }
 
@@ -119,286 +430,3529 @@ impl GpsData {
         self.longitude as f64 / 1e7
     }
 
+    /// Latitude as (degrees, minutes, seconds, 'N'/'S'), all parts non-negative
+    pub fn latitude_dms(&self) -> (i16, u8, f32, char) {
+        to_dms(self.latitude, 'N', 'S')
+    }
+
+    /// Longitude as (degrees, minutes, seconds, 'E'/'W'), all parts non-negative
+    pub fn longitude_dms(&self) -> (i16, u8, f32, char) {
+        to_dms(self.longitude, 'E', 'W')
+    }
+
+    /// Format as a KML `<coordinates>` tuple: "lon,lat,alt"
+    /// Degrees with 7 decimals (the native resolution), altitude above MSL in
+    /// meters with 3. Returns the length written.
+    pub fn to_kml_coord(&self, buf: &mut [u8]) -> Result<usize, KmlError> {
+        if !self.valid {
+            return Err(KmlError::InvalidFix);
+        }
+        let mut writer = SliceWriter { buf, len: 0 };
+        fmt::Write::write_fmt(&mut writer, format_args!("{:.7},{:.7},{:.3}",
+            self.longitude_degrees(), self.latitude_degrees(), self.altitude_meters()))
+            .map_err(|_| KmlError::BufferTooSmall)?;
+        Ok(writer.len)
+    }
+
+    /// Pack the fix into PACKET_LEN bytes for a telemetry link
+    ///
+    /// Little-endian layout: iTOW (0), latitude (4), longitude (8), height
+    /// MSL (12), ground speed (16), heading of motion (20), horizontal
+    /// accuracy (24), fix type (28), satellites (29), flags (30: valid,
+    /// coasting, dead reckoned) and a reserved zero byte (31). Date, time
+    /// and the remaining fields don't fit and are not sent.
+    pub fn to_packet(self) -> [u8; PACKET_LEN] {
+        let mut packet = [0u8; PACKET_LEN];
+        packet[0..4].copy_from_slice(&self.itow_ms.to_le_bytes());
+        packet[4..8].copy_from_slice(&self.latitude.to_le_bytes());
+        packet[8..12].copy_from_slice(&self.longitude.to_le_bytes());
+        packet[12..16].copy_from_slice(&self.height_msl.to_le_bytes());
+        packet[16..20].copy_from_slice(&self.ground_speed.to_le_bytes());
+        packet[20..24].copy_from_slice(&self.heading_motion.to_le_bytes());
+        packet[24..28].copy_from_slice(&self.horizontal_accuracy.to_le_bytes());
+        packet[28] = self.fix_type;
+        packet[29] = self.satellites;
+
+        let mut flags = 0;
+        if self.valid {
+            flags |= PACKET_FLAG_VALID;
+        }
+        if self.coasting {
+            flags |= PACKET_FLAG_COASTING;
+        }
+        if self.dead_reckoned {
+            flags |= PACKET_FLAG_DEAD_RECKONED;
+        }
+        packet[30] = flags;
+        packet
+    }
+
+    /// Unpack a to_packet buffer; fields the packet doesn't carry are left at their defaults
+    pub fn from_packet(packet: &[u8; PACKET_LEN]) -> Self {
+        let word = |offset: usize| [packet[offset], packet[offset + 1], packet[offset + 2], packet[offset + 3]];
+        let flags = packet[30];
+
+        let mut data = Self::new();
+        data.itow_ms = u32::from_le_bytes(word(0));
+        data.latitude = i32::from_le_bytes(word(4));
+        data.longitude = i32::from_le_bytes(word(8));
+        data.height_msl = i32::from_le_bytes(word(12));
+        data.ground_speed = i32::from_le_bytes(word(16));
+        data.heading_motion = i32::from_le_bytes(word(20));
+        data.horizontal_accuracy = u32::from_le_bytes(word(24));
+        data.fix_type = packet[28];
+        data.satellites = packet[29];
+        data.valid = flags & PACKET_FLAG_VALID != 0;
+        data.coasting = flags & PACKET_FLAG_COASTING != 0;
+        data.dead_reckoned = flags & PACKET_FLAG_DEAD_RECKONED != 0;
+        data
+    }
+
+    /// to_packet followed by a little-endian CRC-16/CCITT of the 32 packet bytes
+    pub fn to_packet_crc(self) -> [u8; PACKET_CRC_LEN] {
+        let packet = self.to_packet();
+        let mut framed = [0u8; PACKET_CRC_LEN];
+        framed[..PACKET_LEN].copy_from_slice(&packet);
+        framed[PACKET_LEN..].copy_from_slice(&crc16_ccitt(&packet).to_le_bytes());
+        framed
+    }
+
+    /// Check the CRC of a to_packet_crc buffer and unpack it
+    pub fn from_packet_crc(framed: &[u8; PACKET_CRC_LEN]) -> Result<Self, CrcError> {
+        let mut packet = [0u8; PACKET_LEN];
+        packet.copy_from_slice(&framed[..PACKET_LEN]);
+
+        let received = u16::from_le_bytes([framed[PACKET_LEN], framed[PACKET_LEN + 1]]);
+        let calculated = crc16_ccitt(&packet);
+        if received != calculated {
+            return Err(CrcError { received, calculated });
+        }
+        Ok(Self::from_packet(&packet))
+    }
+
     /// Get altitude in meters as f64
     pub fn altitude_meters(&self) -> f64 {
         self.height_msl as f64 / 1000.0
     }
 
+    /// Get altitude above the WGS84 ellipsoid in meters as f64
+    pub fn altitude_ellipsoid_meters(&self) -> f64 {
+        self.height_ellipsoid_mm as f64 / 1000.0
+    }
+
+    /// Get the geoid separation (ellipsoid height minus MSL height) in meters as f64
+    pub fn geoid_separation_meters(&self) -> f64 {
+        (self.height_ellipsoid_mm - self.height_msl) as f64 / 1000.0
+    }
+
     /// Get ground speed in meters per second as f64
     pub fn speed_ms(&self) -> f64 {
         self.ground_speed as f64 / 1000.0
     }
 
+    /// Get ground speed in knots as f64
+    pub fn speed_knots(&self) -> f64 {
+        self.speed_ms() * MS_TO_KNOTS
+    }
+
+    /// Get ground speed in km/h as f64
+    pub fn speed_kmh(&self) -> f64 {
+        self.speed_ms() * MS_TO_KMH
+    }
+
     /// Get horizontal accuracy in meters as f64
     pub fn horizontal_accuracy_meters(&self) -> f64 {
         self.horizontal_accuracy as f64 / 1000.0
     }
 
+    /// Radius in meters of the uncertainty circle to draw around the fix, None without a valid fix
+    ///
+    /// NAV-PVT only reports a single radial hAcc, so this is a circle, not an
+    /// ellipse; an orientation and per-axis extent would need NAV-COV.
+    pub fn accuracy_circle_meters(&self) -> Option<f64> {
+        self.valid.then(|| self.horizontal_accuracy_meters())
+    }
+
     /// Get vertical accuracy in meters as f64
     pub fn vertical_accuracy_meters(&self) -> f64 {
         self.vertical_accuracy as f64 / 1000.0
     }
-}
 
-// UBX Parser
-pub struct UbxParser {
-    state: UbxParserState,
-    message: UbxMessage,
-    payload_index: usize,
-    calculated_checksum_a: u8,
-    calculated_checksum_b: u8,
-}
+    /// Get speed accuracy in meters per second as f64
+    pub fn speed_accuracy_ms(&self) -> f64 {
+        self.speed_accuracy_mms as f64 / 1000.0
+    }
 
-impl UbxParser {
-    pub fn new() -> Self {
-        Self {
-            state: UbxParserState::WaitingForSync1,
-            message: UbxMessage::new(),
-            payload_index: 0,
-            calculated_checksum_a: 0,
-            calculated_checksum_b: 0,
-        }
+    /// Get heading accuracy in degrees as f64
+    pub fn heading_accuracy_degrees(&self) -> f64 {
+        self.heading_accuracy_1e5_deg as f64 / 1e5
     }
 
-    fn reset(&mut self) {
-        self.state = UbxParserState::WaitingForSync1;
-        self.payload_index = 0;
-        self.calculated_checksum_a = 0;
-        self.calculated_checksum_b = 0;
+    /// Get latitude as a fixed-point integer in 1e-7 degrees (LAT_SCALE)
+    pub fn latitude_e7(&self) -> i32 {
+        self.latitude
     }
 
-    fn calculate_checksum(&mut self, byte: u8) {
-        self.calculated_checksum_a = self.calculated_checksum_a.wrapping_add(byte);
-        self.calculated_checksum_b = self.calculated_checksum_b.wrapping_add(self.calculated_checksum_a);
+    /// Get longitude as a fixed-point integer in 1e-7 degrees (LON_SCALE)
+    pub fn longitude_e7(&self) -> i32 {
+        self.longitude
     }
 
-    pub fn parse_byte(&mut self, byte: u8) -> Option<GpsData> {
-        match self.state {
-            UbxParserState::WaitingForSync1 => {
-                if byte == UBX_SYNC_CHAR_1 {
-                    self.state = UbxParserState::WaitingForSync2;
-                }
-            }
-            UbxParserState::WaitingForSync2 => {
-                if byte == UBX_SYNC_CHAR_2 {
-                    self.state = UbxParserState::ReadingClass;
-                    self.calculated_checksum_a = 0;
-                    self.calculated_checksum_b = 0;
-                } else {
-                    self.reset();
-                }
-            }
-            UbxParserState::ReadingClass => {
-                self.message.class = byte;
-                self.calculate_checksum(byte);
-                self.state = UbxParserState::ReadingId;
-            }
-            UbxParserState::ReadingId => {
-                self.message.id = byte;
-                self.calculate_checksum(byte);
-                self.state = UbxParserState::ReadingLength1;
-            }
-            UbxParserState::ReadingLength1 => {
-                self.message.length = byte as u16;
-                self.calculate_checksum(byte);
-                self.state = UbxParserState::ReadingLength2;
-            }
-            UbxParserState::ReadingLength2 => {
-                self.message.length |= (byte as u16) << 8;
-                self.calculate_checksum(byte);
-                self.payload_index = 0;
-                if self.message.length == 0 {
-                    self.state = UbxParserState::ReadingChecksum1;
-                } else if self.message.length <= 256 {
-                    self.state = UbxParserState::ReadingPayload;
-                } else {
-                    // Message too large, reset
-                    self.reset();
-                }
-            }
-            UbxParserState::ReadingPayload => {
-                if self.payload_index < self.message.length as usize {
-                    self.message.payload[self.payload_index] = byte;
-                    self.payload_index += 1;
-                    self.calculate_checksum(byte);
-                    
-                    if self.payload_index >= self.message.length as usize {
-                        self.state = UbxParserState::ReadingChecksum1;
-                    }
-                } else {
-                    self.reset();
-                }
-            }
-            UbxParserState::ReadingChecksum1 => {
-                self.message.checksum_a = byte;
-                self.state = UbxParserState::ReadingChecksum2;
-            }
-            UbxParserState::ReadingChecksum2 => {
-                self.message.checksum_b = byte;
-                
-                // Verify checksum
-                if self.calculated_checksum_a == self.message.checksum_a &&
-                   self.calculated_checksum_b == self.message.checksum_b {
-                    
-                    // Process the message
-                    let result = self.process_message();
-                    self.reset();
-                    return result;
-                } else {
-                    rprintln!("UBX checksum error");
-                }
-                self.reset();
-            }
-        }
-        None
+    /// Get altitude above mean sea level in millimeters (ALTITUDE_SCALE)
+    pub fn altitude_mm(&self) -> i32 {
+        self.height_msl
     }
 
-    fn process_message(&self) -> Option<GpsData> {
-        if self.message.class == UBX_CLASS_NAV && self.message.id == UBX_NAV_PVT {
-            return self.parse_nav_pvt();
-        }
-        None
+    /// Get ground speed in millimeters per second (SPEED_SCALE)
+    pub fn speed_mms(&self) -> i32 {
+        self.ground_speed
     }
 
-    fn parse_nav_pvt(&self) -> Option<GpsData> {
-        if self.message.length < 84 {
-            return None;
+    /// Get vertical speed in meters per second as f64, positive when ascending
+    /// Returns NAN if the fix is not valid
+    pub fn climb_rate_ms(&self) -> f64 {
+        if !self.valid {
+            return f64::NAN;
         }
+        -(self.velocity_down as f64) / 1000.0
+    }
 
-        let payload = &self.message.payload;
-        
-        // Extract fields from UBX-NAV-PVT payload
-        let year = u16::from_le_bytes([payload[4], payload[5]]);
-        let month = payload[6];
-        let day = payload[7];
-        let hour = payload[8];
-        let minute = payload[9];
-        let second = payload[10];
-        let _valid = payload[11]; // Validity flags
-        
-        let nano = i32::from_le_bytes([payload[16], payload[17], payload[18], payload[19]]);
-        let fix_type = payload[20];
-        let flags = payload[21];
-        let num_sv = payload[23]; // Number of satellites
-        
-        let longitude = i32::from_le_bytes([payload[24], payload[25], payload[26], payload[27]]);
-        let latitude = i32::from_le_bytes([payload[28], payload[29], payload[30], payload[31]]);
-        let _height = i32::from_le_bytes([payload[32], payload[33], payload[34], payload[35]]);
-        let h_msl = i32::from_le_bytes([payload[36], payload[37], payload[38], payload[39]]);
-        let h_acc = u32::from_le_bytes([payload[40], payload[41], payload[42], payload[43]]);
-        let v_acc = u32::from_le_bytes([payload[44], payload[45], payload[46], payload[47]]);
-        
-        let _vel_n = i32::from_le_bytes([payload[48], payload[49], payload[50], payload[51]]);
-        let _vel_e = i32::from_le_bytes([payload[52], payload[53], payload[54], payload[55]]);
-        let _vel_d = i32::from_le_bytes([payload[56], payload[57], payload[58], payload[59]]);
-        let g_speed = i32::from_le_bytes([payload[60], payload[61], payload[62], payload[63]]);
-        
-        // Check if we have a valid 3D fix
-        let has_valid_fix = fix_type >= 3 && (flags & 0x01) != 0;
-        
-        Some(GpsData {
-            valid: has_valid_fix,
-            year,
-            month,
-            day,
-            hour,
-            minute,
-            second,
-            nano,
-            latitude,
-            longitude,
-            height_msl: h_msl,
-            horizontal_accuracy: h_acc,
-            vertical_accuracy: v_acc,
-            ground_speed: g_speed,
-            satellites: num_sv,
-        })
+    /// GPS time of week of the navigation epoch in seconds
+    pub fn time_of_week_seconds(&self) -> f64 {
+        self.itow_ms as f64 / 1000.0
     }
-}
 
-// UBX Configuration Commands
-pub struct UbxConfig;
+    /// Sub-second phase of the navigation epoch in 0.0..1.0
+    ///
+    /// iTOW is the epoch rounded to the millisecond and `nano` the fraction
+    /// of the UTC second to the nanosecond. GPS and UTC seconds start
+    /// together (leap seconds are whole), so the millisecond comes from iTOW
+    /// and the rounding residual below it from `nano`.
+    pub fn fraction_of_second(&self) -> f32 {
+        let mut residual_ns = self.nano.rem_euclid(1_000_000);
+        if residual_ns >= 500_000 {
+            residual_ns -= 1_000_000;
+        }
 
-impl UbxConfig {
-    /// Get UBX command to configure port for UBX-only output (disables NMEA)
-    pub fn get_port_config_ubx_only() -> [u8; 28] {
-        [
-            0xB5, 0x62,  // UBX sync chars
-            0x06, 0x00,  // Class CFG, ID PRT (Port configuration)
-            0x14, 0x00,  // Length (20 bytes)
-            0x01,        // Port ID (1 = UART1)
-            0x00,        // Reserved
-            0x00, 0x00,  // TX Ready pin config
-            0x00, 0x23, 0x00, 0x23,  // UART mode (8N1)
-            0x00, 0x96, 0x00, 0x00,  // Baud rate (38400)
-            0x01, 0x00,  // Input protocols (UBX only)
-            0x01, 0x00,  // Output protocols (UBX only)
-            0x00, 0x00,  // Flags
-            0x00, 0x00,  // Reserved
-            0x41, 0x28   // Checksum
-        ]
+        let mut ms = (self.itow_ms % 1000) as f32 + residual_ns as f32 / 1_000_000.0;
+        if ms < 0.0 {
+            ms += 1000.0;
+        } else if ms >= 1000.0 {
+            ms -= 1000.0;
+        }
+        ms / 1000.0
     }
 
-    /// Get UBX command to enable NAV-PVT messages
-    pub fn get_enable_nav_pvt() -> [u8; 11] {
-        [
-            0xB5, 0x62,  // UBX sync chars
-            0x06, 0x01,  // Class CFG, ID MSG
-            0x03, 0x00,  // Length (3 bytes)
-            0x01, 0x07,  // Message Class/ID (NAV-PVT)
-            0x01,        // Rate (1 = output every solution)
-            0x13, 0x51   // Checksum
-        ]
+    /// Check if the UTC date and time can be used for clock synchronisation
+    /// Requires valid date, valid time and a fully resolved time, but not a
+    /// position fix, so time can be synced before the receiver has a 3D lock
+    pub fn time_is_valid(&self) -> bool {
+        self.validity.date_valid && self.validity.time_valid && self.validity.fully_resolved
     }
-}
 
-// GPS Manager - handles all GPS operations
-pub struct GpsManager {
-    parser: UbxParser,
-    last_data: GpsData,
+    /// Course over ground in degrees (0-360) derived from the NED velocity
+    /// Returns None if the fix is not valid or slower than COURSE_MIN_SPEED_MMS
+    pub fn course_over_ground(&self) -> Option<f64> {
+        self.course_over_ground_above(COURSE_MIN_SPEED_MMS)
+    }
+
+    /// Course over ground in degrees (0-360) with a custom speed floor in mm/s
+    pub fn course_over_ground_above(&self, min_speed_mms: i32) -> Option<f64> {
+        if !self.valid || self.ground_speed < min_speed_mms {
+            return None;
+        }
+
+        let course = libm::atan2(self.velocity_east as f64, self.velocity_north as f64).to_degrees();
+        if course < 0.0 {
+            Some(course + 360.0)
+        } else {
+            Some(course)
+        }
+    }
+
+    /// Time of day of the fix in seconds, including the nanosecond fraction
+    fn time_of_day_seconds(&self) -> f64 {
+        (self.hour as u32 * 3600 + self.minute as u32 * 60 + self.second as u32) as f64
+            + self.nano as f64 / 1e9
+    }
+
+    /// Extrapolate this fix by its NED velocity over `elapsed_ms`
+    ///
+    /// Uses a flat-earth approximation at the current latitude, which is
+    /// fine for the few meters covered in a short outage. Date/time fields
+    /// are not advanced. The result has `dead_reckoned` set; past
+    /// DEAD_RECKON_MAX_MS it is also marked invalid and not moved. An invalid
+    /// fix is returned unchanged.
+    pub fn dead_reckon(&self, elapsed_ms: u32) -> GpsData {
+        let mut result = *self;
+        if !self.valid {
+            return result;
+        }
+
+        result.dead_reckoned = true;
+        if elapsed_ms > DEAD_RECKON_MAX_MS {
+            result.valid = false;
+            return result;
+        }
+
+        let dt = elapsed_ms as f64 / 1000.0;
+        let north_m = self.velocity_north as f64 / 1000.0 * dt;
+        let east_m = self.velocity_east as f64 / 1000.0 * dt;
+
+        let lat_rad = self.latitude_degrees().to_radians();
+        let d_lat_deg = (north_m / EARTH_RADIUS_M).to_degrees();
+        let d_lon_deg = (east_m / (EARTH_RADIUS_M * libm::cos(lat_rad))).to_degrees();
+
+        result.latitude = self.latitude + libm::round(d_lat_deg / LAT_SCALE) as i32;
+        result.longitude = self.longitude + libm::round(d_lon_deg / LON_SCALE) as i32;
+        let down_mm = libm::round(self.velocity_down as f64 * dt) as i32;
+        result.height_msl = self.height_msl - down_mm;
+        result.height_ellipsoid_mm = self.height_ellipsoid_mm - down_mm;
+        result
+    }
+
+    /// Summarize fix health from the fix type, satellite count and horizontal accuracy
+    /// See the QUALITY_* constants for the tier thresholds
+    pub fn quality(&self) -> FixQuality {
+        // 3 = 3D, 4 = GNSS + dead reckoning
+        let is_3d = match self.fix_type {
+            2 => false,
+            3 | 4 => true,
+            _ => return FixQuality::NoFix,
+        };
+
+        let meets = |min_sats: u8, max_hacc_mm: u32| {
+            is_3d && self.satellites >= min_sats && self.horizontal_accuracy < max_hacc_mm
+        };
+        if meets(QUALITY_EXCELLENT_MIN_SATS, QUALITY_EXCELLENT_MAX_HACC_MM) {
+            FixQuality::Excellent
+        } else if meets(QUALITY_GOOD_MIN_SATS, QUALITY_GOOD_MAX_HACC_MM) {
+            FixQuality::Good
+        } else if meets(QUALITY_FAIR_MIN_SATS, QUALITY_FAIR_MAX_HACC_MM) {
+            FixQuality::Fair
+        } else {
+            FixQuality::Poor
+        }
+    }
+
+    /// Check if the solution uses at least `min` satellites
+    pub fn has_sufficient_satellites(&self, min: u8) -> bool {
+        self.satellites >= min
+    }
+
+    /// Great-circle distance to another fix in meters (haversine)
+    pub fn distance_to(&self, other: &GpsData) -> f64 {
+        haversine_distance_m(self.latitude, self.longitude, other.latitude, other.longitude)
+    }
 }
 
-impl GpsManager {
+impl Default for GpsData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Fluent construction of a GpsData, mainly for test fixtures
+///
+/// Starts from `GpsData::new()` (no fix); each setter fills the matching
+/// raw field, converting from degrees and meters.
+pub struct GpsDataBuilder {
+    data: GpsData,
+}
+
+impl GpsDataBuilder {
     pub fn new() -> Self {
+        Self { data: GpsData::new() }
+    }
+
+    /// Latitude in degrees, positive north
+    pub fn lat(mut self, degrees: f64) -> Self {
+        self.data.latitude = libm::round(degrees * 1e7) as i32;
+        self
+    }
+
+    /// Longitude in degrees, positive east
+    pub fn lon(mut self, degrees: f64) -> Self {
+        self.data.longitude = libm::round(degrees * 1e7) as i32;
+        self
+    }
+
+    /// Height above mean sea level in meters
+    pub fn alt(mut self, meters: f64) -> Self {
+        self.data.height_msl = libm::round(meters * 1000.0) as i32;
+        self
+    }
+
+    pub fn sats(mut self, satellites: u8) -> Self {
+        self.data.satellites = satellites;
+        self
+    }
+
+    /// Mark the fix valid, as a 3D fix
+    pub fn valid(mut self) -> Self {
+        self.data.valid = true;
+        self.data.fix_type = 3;
+        self
+    }
+
+    pub fn build(self) -> GpsData {
+        self.data
+    }
+}
+
+impl Default for GpsDataBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Split a coordinate in 1e-7 degrees into degrees, minutes, seconds and hemisphere
+/// Integer math down to the seconds so no precision is lost before the f32
+fn to_dms(value: i32, positive: char, negative: char) -> (i16, u8, f32, char) {
+    let hemisphere = if value < 0 { negative } else { positive };
+    let abs = value.unsigned_abs();
+
+    let degrees = abs / 10_000_000;
+    let minutes_1e7 = (abs % 10_000_000) * 60;
+    let minutes = minutes_1e7 / 10_000_000;
+    let seconds = ((minutes_1e7 % 10_000_000) * 60) as f32 / 1e7;
+
+    (degrees as i16, minutes as u8, seconds, hemisphere)
+}
+
+// Failures of GpsData::to_kml_coord
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum KmlError {
+    InvalidFix,     // No valid fix to format
+    BufferTooSmall, // The whole tuple doesn't fit in the buffer
+}
+
+// Telemetry packet rejected by GpsData::from_packet_crc
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CrcError {
+    pub received: u16,   // CRC carried by the packet
+    pub calculated: u16, // CRC over the bytes that arrived
+}
+
+/// CRC-16/CCITT-FALSE (polynomial 0x1021, initial value 0xFFFF)
+/// Bitwise rather than table-driven, trading speed for 512 bytes of flash
+pub fn crc16_ccitt(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+// fmt::Write into a fixed byte buffer, failing once it is full
+struct SliceWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl fmt::Write for SliceWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        let dest = self.buf.get_mut(self.len..end).ok_or(fmt::Error)?;
+        dest.copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// Haversine distance in meters between two points given in 1e-7 degrees
+fn haversine_distance_m(lat1: i32, lon1: i32, lat2: i32, lon2: i32) -> f64 {
+    let to_rad = |v: i32| (v as f64 / 1e7).to_radians();
+    let (lat1, lat2) = (to_rad(lat1), to_rad(lat2));
+    let d_lat = lat2 - lat1;
+    let d_lon = to_rad(lon2) - to_rad(lon1);
+
+    let sin_lat = libm::sin(d_lat / 2.0);
+    let sin_lon = libm::sin(d_lon / 2.0);
+    let a = sin_lat * sin_lat + libm::cos(lat1) * libm::cos(lat2) * sin_lon * sin_lon;
+    let c = 2.0 * libm::atan2(libm::sqrt(a), libm::sqrt(1.0 - a));
+
+    EARTH_RADIUS_M * c
+}
+
+/// Initial great-circle bearing in degrees (0-360) from point 1 to point 2, given in 1e-7 degrees
+fn initial_bearing_deg(lat1: i32, lon1: i32, lat2: i32, lon2: i32) -> f64 {
+    let to_rad = |v: i32| (v as f64 / 1e7).to_radians();
+    let (lat1, lat2) = (to_rad(lat1), to_rad(lat2));
+    let d_lon = to_rad(lon2) - to_rad(lon1);
+
+    let y = libm::sin(d_lon) * libm::cos(lat2);
+    let x = libm::cos(lat1) * libm::sin(lat2) - libm::sin(lat1) * libm::cos(lat2) * libm::cos(d_lon);
+    let bearing = libm::atan2(y, x).to_degrees();
+    if bearing < 0.0 {
+        bearing + 360.0
+    } else {
+        bearing
+    }
+}
+
+impl fmt::Display for GpsData {
+    /// Three-line fix summary, or a single line when there is no valid fix
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.valid {
+            writeln!(f, "GPS Fix: {}/{:02}/{:02} {:02}:{:02}:{:02}",
+                     self.year, self.month, self.day, self.hour, self.minute, self.second)?;
+            writeln!(f, "Position: {:.7}°, {:.7}° (±{:.1}m)",
+                     self.latitude_degrees(), self.longitude_degrees(), self.horizontal_accuracy_meters())?;
+            write!(f, "Altitude: {:.1}m, Speed: {:.1}m/s, Sats: {}",
+                   self.altitude_meters(), self.speed_ms(), self.satellites)
+        } else {
+            write!(f, "GPS: No valid fix")
+        }
+    }
+}
+
+// NAV-TIMEUTC validity flags (payload byte 19)
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TimeUtcValid {
+    pub tow_valid: bool,  // validTOW: GPS time of week is valid
+    pub week_valid: bool, // validWKN: GPS week number is valid
+    pub utc_valid: bool,  // validUTC: UTC time is valid (leap seconds known)
+    pub utc_standard: u8, // UTC standard identifier (e.g. 3 = USNO, 0 = not available)
+}
+
+impl TimeUtcValid {
+    /// Decode the NAV-TIMEUTC `valid` bit field
+    pub fn from_bits(bits: u8) -> Self {
         Self {
-            parser: UbxParser::new(),
-            last_data: GpsData::new(),
+            tow_valid: bits & 0x01 != 0,
+            week_valid: bits & 0x02 != 0,
+            utc_valid: bits & 0x04 != 0,
+            utc_standard: bits >> 4,
         }
     }
+}
 
-    /// Get UBX configuration commands 
-    /// Returns the port config and PVT enable commands
-    pub fn get_config_commands(&self) -> ([u8; 28], [u8; 11]) {
-        rprintln!("Preparing UBX configuration commands...");
-        
-        let ubx_cfg_port = UbxConfig::get_port_config_ubx_only();
-        let ubx_cfg_pvt = UbxConfig::get_enable_nav_pvt();
-        
-        (ubx_cfg_port, ubx_cfg_pvt)
+// UTC time from UBX-NAV-TIMEUTC, available without a position fix
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct UtcTime {
+    pub itow: u32,          // GPS time of week in ms
+    pub time_accuracy: u32, // Time accuracy estimate in ns
+    pub nano: i32,          // Fraction of second in ns (-1e9..1e9)
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub valid: TimeUtcValid,
+}
+
+// Port settings reported by the receiver in reply to a CFG-PRT poll
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PortConfig {
+    pub port: u8,       // Port ID (1 = UART1)
+    pub baud: u32,      // Baud rate in bit/s
+    pub in_proto: u16,  // Accepted input protocols (PROTO_* mask)
+    pub out_proto: u16, // Enabled output protocols (PROTO_* mask)
+}
+
+// Payload bytes kept by a raw frame capture; longer payloads are truncated
+pub const RAW_PAYLOAD_CAPACITY: usize = 128;
+
+// Verbatim copy of a frame the parser doesn't decode
+#[derive(Clone, Copy)]
+pub struct RawFrame {
+    pub class: u8,
+    pub id: u8,
+    pub length: u16, // Payload length from the frame header
+    data: [u8; RAW_PAYLOAD_CAPACITY],
+}
+
+impl RawFrame {
+    /// Get the captured payload bytes (at most RAW_PAYLOAD_CAPACITY)
+    pub fn payload(&self) -> &[u8] {
+        &self.data[..(self.length as usize).min(RAW_PAYLOAD_CAPACITY)]
     }
 
-    /// Update GPS data by reading a single byte
-    /// Returns Some(GpsData) if new GPS data is available
-    pub fn process_byte(&mut self, byte: u8) -> Option<GpsData> {
-        if let Some(gps_data) = self.parser.parse_byte(byte) {
-            self.last_data = gps_data;
-            return Some(gps_data);
+    /// Check if the payload was longer than the capture buffer
+    pub fn is_truncated(&self) -> bool {
+        self.length as usize > RAW_PAYLOAD_CAPACITY
+    }
+}
+
+// Events produced by the UBX parser for each valid frame
+#[derive(Clone, Copy)]
+pub enum UbxEvent {
+    /// Decoded UBX-NAV-PVT solution
+    NavPvt(GpsData),
+    /// NAV-PVT solution whose horizontal accuracy is worse than set_min_accuracy allows
+    LowQualityFix(GpsData),
+    /// Decoded UBX-NAV-TIMEUTC time solution
+    NavTimeUtc(UtcTime),
+    /// Known message whose payload length doesn't match what the crate decodes
+    /// Usually a firmware/protocol version difference, e.g. an M8 NAV-PVT
+    UnexpectedLength { class: u8, id: u8, got: u16, expected: u16 },
+    /// Decoded UBX-CFG-PRT reply to build_cfg_prt_poll
+    CfgPrt(PortConfig),
+    /// UBX-ACK-ACK: the receiver accepted the command with this class/id
+    Ack { class: u8, id: u8 },
+    /// UBX-ACK-NAK: the receiver rejected the command with this class/id
+    Nak { class: u8, id: u8 },
+    /// Valid frame of a message class/id the parser doesn't decode
+    Other { class: u8, id: u8 },
+    /// Undecoded frame with its payload, emitted instead of Other when raw capture is on
+    Raw(RawFrame),
+}
+
+// Number of message handlers UbxParser::on_message can hold
+pub const MAX_MESSAGE_HANDLERS: usize = 4;
+
+// Message callback, called with the checksum-verified payload
+pub type MessageHandler = fn(&[u8]);
+
+// Framing errors reported to the UbxParser::on_error sink
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UbxError {
+    /// Frame dropped on a checksum mismatch
+    Checksum { class: u8, id: u8 },
+    /// Header announced a payload larger than the parser buffer
+    Oversize { length: u16 },
+    /// Strict sync rejected a header with an unknown class
+    UnknownClass(u8),
+}
+
+// Error callback set with UbxParser::on_error
+pub type ErrorHandler = fn(UbxError);
+
+#[derive(Clone, Copy)]
+struct HandlerEntry {
+    class: u8,
+    id: u8,
+    handler: MessageHandler,
+}
+
+// Parser health counters
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ParserStats {
+    pub frames: u32,           // Frames with a valid checksum
+    pub checksum_errors: u32,  // Frames dropped on a checksum mismatch
+    pub bytes_skipped: u32,    // Bytes discarded while looking for B5 62
+    pub rejected_headers: u32, // Syncs dropped for an oversized length or (strict) unknown class
+}
+
+impl ParserStats {
+    pub const fn new() -> Self {
+        Self {
+            frames: 0,
+            checksum_errors: 0,
+            bytes_skipped: 0,
+            rejected_headers: 0,
         }
-        None
     }
+}
 
-    /// Get the last received GPS data
-    pub fn last_data(&self) -> &GpsData {
-        &self.last_data
+impl Default for ParserStats {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    /// Check if GPS has a valid fix
-    pub fn has_fix(&self) -> bool {
-        self.last_data.valid
+// Checksum failures kept by UbxParser with the `diagnostics` feature,
+// and payload bytes stored per failure
+#[cfg(feature = "diagnostics")]
+pub const MAX_CHECKSUM_FAILURES: usize = 4;
+#[cfg(feature = "diagnostics")]
+pub const FAILURE_PAYLOAD_BYTES: usize = 8;
+
+// Snapshot of a frame dropped on a checksum mismatch, for diagnosing wiring:
+// a single flipped bit leaves class/id/length plausible, a framing problem
+// usually doesn't
+#[cfg(feature = "diagnostics")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ChecksumFailure {
+    pub class: u8,
+    pub id: u8,
+    pub length: u16,
+    pub payload: [u8; FAILURE_PAYLOAD_BYTES], // First payload bytes, zero-padded
+    pub received: [u8; 2],                    // CK_A/CK_B from the frame
+    pub calculated: [u8; 2],                  // CK_A/CK_B over the bytes received
+}
+
+// UBX Parser
+//
+// N is the payload buffer size in bytes. Frames announcing a longer payload
+// are dropped, so a smaller N saves RAM when only short messages are needed;
+// NAV-PVT needs at least NAV_PVT_MIN_LENGTH.
+pub struct UbxParser<const N: usize = UBX_DEFAULT_PAYLOAD_SIZE> {
+    state: UbxParserState,
+    message: UbxMessage<N>,
+    payload_index: usize,
+    calculated_checksum_a: u8,
+    calculated_checksum_b: u8,
+    raw_capture: bool,
+    handlers: [Option<HandlerEntry>; MAX_MESSAGE_HANDLERS],
+    strict_sync: bool,
+    strict_length: bool,
+    stats: ParserStats,
+    error_handler: Option<ErrorHandler>,
+    max_hacc_mm: u32, // NAV-PVT fixes above this horizontal accuracy are LowQualityFix
+    #[cfg(feature = "diagnostics")]
+    failures: heapless::HistoryBuffer<ChecksumFailure, MAX_CHECKSUM_FAILURES>,
+}
+
+impl<const N: usize> UbxParser<N> {
+    pub const fn new() -> Self {
+        Self {
+            state: UbxParserState::WaitingForSync1,
+            message: UbxMessage::new(),
+            payload_index: 0,
+            calculated_checksum_a: 0,
+            calculated_checksum_b: 0,
+            raw_capture: false,
+            handlers: [None; MAX_MESSAGE_HANDLERS],
+            strict_sync: false,
+            strict_length: false,
+            stats: ParserStats::new(),
+            error_handler: None,
+            max_hacc_mm: u32::MAX,
+            #[cfg(feature = "diagnostics")]
+            failures: heapless::HistoryBuffer::new(),
+        }
     }
 
-    /// Get number of satellites
-    pub fn satellite_count(&self) -> u8 {
-        self.last_data.satellites
+    /// Report framing errors to `handler` as they happen
+    /// The parser itself never logs them; the stats counters are updated either way
+    pub fn on_error(&mut self, handler: ErrorHandler) {
+        self.error_handler = Some(handler);
+    }
+
+    fn report(&self, error: UbxError) {
+        if let Some(handler) = self.error_handler {
+            handler(error);
+        }
+    }
+
+    /// Only accept a B5 62 sync when it is followed by a known UBX class
+    ///
+    /// In both modes a frame only starts at a B5 62 pair with nothing in
+    /// between: any other byte after the B5 restarts the search, and every
+    /// byte passed over while looking for sync is counted in `bytes_skipped`.
+    /// When NMEA cuts into a UBX frame the parser resyncs in the middle of
+    /// the stream, and any B5 62 inside later binary data starts a bogus
+    /// frame that ends in a checksum error. In strict mode such a sync is
+    /// dropped at the class byte (counted in `rejected_headers`) and the
+    /// search continues, so real corruption stays visible in `checksum_errors`.
+    pub fn set_strict_sync(&mut self, strict: bool) {
+        self.strict_sync = strict;
+    }
+
+    /// Require decoded messages to have exactly their current payload length
+    ///
+    /// NAV-PVT (NAV_PVT_LENGTH), NAV-DOP (NAV_DOP_LENGTH) and NAV-TIMEUTC
+    /// with any other length produce UbxEvent::UnexpectedLength instead of
+    /// being decoded. Off by default, in which case NAV-PVT is decoded from
+    /// any payload long enough for its fields (e.g. the 84-byte M8 version)
+    /// and only payloads too short to decode are reported.
+    pub fn set_strict_length(&mut self, strict: bool) {
+        self.strict_length = strict;
+    }
+
+    /// Counters since creation or the last reset_stats
+    pub fn stats(&self) -> ParserStats {
+        self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = ParserStats::new();
+    }
+
+    /// Subscribe a handler to a message class/id
+    ///
+    /// Handlers run from `parse_byte` for every valid frame of that type,
+    /// before the built-in decoding (NAV-PVT and NAV-TIMEUTC still produce
+    /// their events). Several handlers may share a class/id. Handlers are
+    /// plain function pointers, so keep any state they need in a static.
+    /// Returns false if all MAX_MESSAGE_HANDLERS slots are taken.
+    pub fn on_message(&mut self, class: u8, id: u8, handler: MessageHandler) -> bool {
+        match self.handlers.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => {
+                *slot = Some(HandlerEntry { class, id, handler });
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Report NAV-PVT fixes with a horizontal accuracy worse than `meters`
+    /// as UbxEvent::LowQualityFix instead of NavPvt
+    ///
+    /// Off by default; `f32::INFINITY` (or anything above ~4295 km, the
+    /// largest hAcc NAV-PVT can report) turns the filter off again.
+    /// Returns false and leaves the setting unchanged for NaN or a negative
+    /// distance.
+    pub fn set_min_accuracy(&mut self, meters: f32) -> bool {
+        if meters.is_nan() || meters < 0.0 {
+            return false;
+        }
+        self.max_hacc_mm = if meters >= u32::MAX as f32 / 1000.0 {
+            u32::MAX
+        } else {
+            (meters * 1000.0) as u32
+        };
+        true
+    }
+
+    /// Emit UbxEvent::Raw with a payload copy for undecoded messages
+    pub fn set_raw_capture(&mut self, enabled: bool) {
+        self.raw_capture = enabled;
+    }
+
+    fn reset(&mut self) {
+        self.state = UbxParserState::WaitingForSync1;
+        self.payload_index = 0;
+        self.calculated_checksum_a = 0;
+        self.calculated_checksum_b = 0;
+    }
+
+    /// Reset after a failed frame without losing a sync char
+    /// If the offending byte is itself 0xB5 it may start the next frame, so stay armed
+    fn resync(&mut self, byte: u8) {
+        self.reset();
+        if byte == UBX_SYNC_CHAR_1 {
+            self.state = UbxParserState::WaitingForSync2;
+        } else {
+            self.stats.bytes_skipped = self.stats.bytes_skipped.wrapping_add(1);
+        }
+    }
+
+    fn calculate_checksum(&mut self, byte: u8) {
+        self.calculated_checksum_a = self.calculated_checksum_a.wrapping_add(byte);
+        self.calculated_checksum_b = self.calculated_checksum_b.wrapping_add(self.calculated_checksum_a);
+    }
+
+    /// Feed a whole buffer, e.g. a logged .ubx capture, calling `on_event` per event
+    /// Parser state carries over between calls, so a capture can be fed in pieces
+    pub fn parse_all(&mut self, data: &[u8], mut on_event: impl FnMut(UbxEvent)) {
+        for &byte in data {
+            if let Some(event) = self.parse_byte(byte) {
+                on_event(event);
+            }
+        }
+    }
+
+    pub fn parse_byte(&mut self, byte: u8) -> Option<UbxEvent> {
+        match self.state {
+            UbxParserState::WaitingForSync1 => {
+                if byte == UBX_SYNC_CHAR_1 {
+                    self.state = UbxParserState::WaitingForSync2;
+                } else {
+                    self.stats.bytes_skipped = self.stats.bytes_skipped.wrapping_add(1);
+                }
+            }
+            UbxParserState::WaitingForSync2 => {
+                if byte == UBX_SYNC_CHAR_2 {
+                    self.state = UbxParserState::ReadingClass;
+                    self.calculated_checksum_a = 0;
+                    self.calculated_checksum_b = 0;
+                } else {
+                    // The armed 0xB5 turned out to be noise
+                    self.stats.bytes_skipped = self.stats.bytes_skipped.wrapping_add(1);
+                    self.resync(byte);
+                }
+            }
+            UbxParserState::ReadingClass => {
+                if self.strict_sync && matches!(UbxClass::from(byte), UbxClass::Unknown(_)) {
+                    self.stats.rejected_headers = self.stats.rejected_headers.wrapping_add(1);
+                    self.report(UbxError::UnknownClass(byte));
+                    self.resync(byte);
+                    return None;
+                }
+                self.message.class = byte;
+                self.calculate_checksum(byte);
+                self.state = UbxParserState::ReadingId;
+            }
+            UbxParserState::ReadingId => {
+                self.message.id = byte;
+                self.calculate_checksum(byte);
+                self.state = UbxParserState::ReadingLength1;
+            }
+            UbxParserState::ReadingLength1 => {
+                self.message.length = byte as u16;
+                self.calculate_checksum(byte);
+                self.state = UbxParserState::ReadingLength2;
+            }
+            UbxParserState::ReadingLength2 => {
+                self.message.length |= (byte as u16) << 8;
+                self.calculate_checksum(byte);
+                self.payload_index = 0;
+                if self.message.length == 0 {
+                    self.state = UbxParserState::ReadingChecksum1;
+                } else if self.message.length as usize <= N {
+                    self.state = UbxParserState::ReadingPayload;
+                } else {
+                    // Message too large, reset
+                    self.stats.rejected_headers = self.stats.rejected_headers.wrapping_add(1);
+                    self.report(UbxError::Oversize { length: self.message.length });
+                    self.resync(byte);
+                }
+            }
+            UbxParserState::ReadingPayload => {
+                // Hard bound on the buffer, independent of the length check above
+                if self.payload_index >= N {
+                    self.reset();
+                    return None;
+                }
+                if self.payload_index < self.message.length as usize {
+                    self.message.payload[self.payload_index] = byte;
+                    self.payload_index += 1;
+                    self.calculate_checksum(byte);
+                    
+                    if self.payload_index >= self.message.length as usize {
+                        self.state = UbxParserState::ReadingChecksum1;
+                    }
+                } else {
+                    self.resync(byte);
+                }
+            }
+            UbxParserState::ReadingChecksum1 => {
+                self.message.checksum_a = byte;
+                self.state = UbxParserState::ReadingChecksum2;
+            }
+            UbxParserState::ReadingChecksum2 => {
+                self.message.checksum_b = byte;
+                
+                // Verify checksum
+                if self.calculated_checksum_a == self.message.checksum_a &&
+                   self.calculated_checksum_b == self.message.checksum_b {
+                    
+                    // Process the message
+                    self.stats.frames = self.stats.frames.wrapping_add(1);
+                    let result = self.process_message();
+                    self.reset();
+                    return result;
+                }
+
+                self.stats.checksum_errors = self.stats.checksum_errors.wrapping_add(1);
+                #[cfg(feature = "diagnostics")]
+                self.record_failure();
+                self.report(UbxError::Checksum {
+                    class: self.message.class,
+                    id: self.message.id,
+                });
+                self.resync(byte);
+            }
+        }
+        None
+    }
+
+    /// The last MAX_CHECKSUM_FAILURES frames dropped on a checksum mismatch, oldest first
+    #[cfg(feature = "diagnostics")]
+    pub fn recent_failures(&self) -> impl Iterator<Item = &ChecksumFailure> + '_ {
+        self.failures.oldest_ordered()
+    }
+
+    #[cfg(feature = "diagnostics")]
+    fn record_failure(&mut self) {
+        let mut payload = [0u8; FAILURE_PAYLOAD_BYTES];
+        let copy_len = (self.message.length as usize).min(FAILURE_PAYLOAD_BYTES);
+        payload[..copy_len].copy_from_slice(&self.message.payload[..copy_len]);
+
+        self.failures.write(ChecksumFailure {
+            class: self.message.class,
+            id: self.message.id,
+            length: self.message.length,
+            payload,
+            received: [self.message.checksum_a, self.message.checksum_b],
+            calculated: [self.calculated_checksum_a, self.calculated_checksum_b],
+        });
+    }
+
+    /// Drive the parser over a buffer of received bytes (e.g. a DMA block)
+    /// Yields each completed event in order without copying the data
+    pub fn feed<'a>(&'a mut self, data: &'a [u8]) -> impl Iterator<Item = UbxEvent> + 'a {
+        data.iter().filter_map(move |&byte| self.parse_byte(byte))
+    }
+
+    /// Parse at most `max_bytes` of `data`, stopping early at the first event
+    ///
+    /// Returns how many bytes were consumed and the event, if one completed.
+    /// Bounds the time spent per call in a cooperative loop: call again with
+    /// `&data[consumed..]` to resume, since the parser keeps any partial frame.
+    pub fn feed_budgeted(&mut self, data: &[u8], max_bytes: usize) -> (usize, Option<UbxEvent>) {
+        for (index, &byte) in data.iter().take(max_bytes).enumerate() {
+            if let Some(event) = self.parse_byte(byte) {
+                return (index + 1, Some(event));
+            }
+        }
+        (data.len().min(max_bytes), None)
+    }
+
+    fn process_message(&self) -> Option<UbxEvent> {
+        let payload = &self.message.payload[..self.message.length as usize];
+        for entry in self.handlers.iter().flatten() {
+            if entry.class == self.message.class && entry.id == self.message.id {
+                (entry.handler)(payload);
+            }
+        }
+
+        if UbxClass::from(self.message.class) == UbxClass::Nav {
+            let (expected, min) = match NavId::from(self.message.id) {
+                NavId::Pvt => (Some(NAV_PVT_LENGTH), NAV_PVT_MIN_LENGTH),
+                NavId::Dop => (Some(NAV_DOP_LENGTH), 0),
+                NavId::TimeUtc => (Some(NAV_TIMEUTC_LENGTH), NAV_TIMEUTC_LENGTH),
+                _ => (None, 0),
+            };
+            let got = self.message.length;
+            if let Some(expected) = expected.filter(|&e| got < min || (self.strict_length && got != e)) {
+                return Some(UbxEvent::UnexpectedLength {
+                    class: self.message.class,
+                    id: self.message.id,
+                    got,
+                    expected,
+                });
+            }
+
+            match NavId::from(self.message.id) {
+                NavId::Pvt => {
+                    return self.parse_nav_pvt().map(|fix| {
+                        if fix.horizontal_accuracy > self.max_hacc_mm {
+                            UbxEvent::LowQualityFix(fix)
+                        } else {
+                            UbxEvent::NavPvt(fix)
+                        }
+                    });
+                }
+                NavId::TimeUtc => return self.parse_nav_timeutc().map(UbxEvent::NavTimeUtc),
+                _ => {}
+            }
+        }
+        if UbxClass::from(self.message.class) == UbxClass::Cfg
+            && self.message.id == UBX_CFG_PRT
+            && self.message.length >= UBX_CFG_PRT_LENGTH
+        {
+            return Some(UbxEvent::CfgPrt(PortConfig {
+                port: payload[0],
+                baud: u32::from_le_bytes([payload[8], payload[9], payload[10], payload[11]]),
+                in_proto: u16::from_le_bytes([payload[12], payload[13]]),
+                out_proto: u16::from_le_bytes([payload[14], payload[15]]),
+            }));
+        }
+        if UbxClass::from(self.message.class) == UbxClass::Ack && self.message.length >= UBX_ACK_LENGTH {
+            let (class, id) = (payload[0], payload[1]);
+            match self.message.id {
+                UBX_ACK_ACK => return Some(UbxEvent::Ack { class, id }),
+                UBX_ACK_NAK => return Some(UbxEvent::Nak { class, id }),
+                _ => {}
+            }
+        }
+        if self.raw_capture {
+            let length = self.message.length;
+            let copy_len = (length as usize).min(RAW_PAYLOAD_CAPACITY);
+            let mut data = [0u8; RAW_PAYLOAD_CAPACITY];
+            data[..copy_len].copy_from_slice(&self.message.payload[..copy_len]);
+
+            return Some(UbxEvent::Raw(RawFrame {
+                class: self.message.class,
+                id: self.message.id,
+                length,
+                data,
+            }));
+        }
+
+        Some(UbxEvent::Other {
+            class: self.message.class,
+            id: self.message.id,
+        })
+    }
+
+    fn parse_nav_pvt(&self) -> Option<GpsData> {
+        // Only read offsets that were actually received this frame, the rest
+        // of the payload buffer still holds bytes from earlier messages
+        if self.message.length < NAV_PVT_MIN_LENGTH {
+            return None;
+        }
+
+        let payload = &self.message.payload;
+        
+        // Extract fields from UBX-NAV-PVT payload
+        let itow = u32::from_le_bytes([payload[0], payload[1], payload[2], payload[3]]);
+        let year = u16::from_le_bytes([payload[4], payload[5]]);
+        let month = payload[6];
+        let day = payload[7];
+        let hour = payload[8];
+        let minute = payload[9];
+        let second = payload[10];
+        let mut validity = ValidFlags::from_bits(payload[11]);
+        if year < NAV_PVT_MIN_YEAR || month == 0 || month > 12 || day == 0 {
+            // Placeholder date: keep the position, just stop trusting the timestamp
+            validity.date_valid = false;
+        }
+        
+        let nano = i32::from_le_bytes([payload[16], payload[17], payload[18], payload[19]]);
+        let fix_type = payload[20];
+        let flags = payload[21];
+        let num_sv = payload[23]; // Number of satellites
+        
+        let longitude = i32::from_le_bytes([payload[24], payload[25], payload[26], payload[27]]);
+        let latitude = i32::from_le_bytes([payload[28], payload[29], payload[30], payload[31]]);
+        let height = i32::from_le_bytes([payload[32], payload[33], payload[34], payload[35]]);
+        let h_msl = i32::from_le_bytes([payload[36], payload[37], payload[38], payload[39]]);
+        let h_acc = u32::from_le_bytes([payload[40], payload[41], payload[42], payload[43]]);
+        let v_acc = u32::from_le_bytes([payload[44], payload[45], payload[46], payload[47]]);
+        
+        let vel_n = i32::from_le_bytes([payload[48], payload[49], payload[50], payload[51]]);
+        let vel_e = i32::from_le_bytes([payload[52], payload[53], payload[54], payload[55]]);
+        let vel_d = i32::from_le_bytes([payload[56], payload[57], payload[58], payload[59]]);
+        let g_speed = i32::from_le_bytes([payload[60], payload[61], payload[62], payload[63]]);
+        let head_mot = if self.message.length >= NAV_PVT_HEAD_MOT_LENGTH {
+            i32::from_le_bytes([payload[64], payload[65], payload[66], payload[67]])
+        } else {
+            0
+        };
+        let (s_acc, head_acc) = if self.message.length >= NAV_PVT_ACCURACY_LENGTH {
+            (
+                u32::from_le_bytes([payload[68], payload[69], payload[70], payload[71]]),
+                u32::from_le_bytes([payload[72], payload[73], payload[74], payload[75]]),
+            )
+        } else {
+            (0, 0)
+        };
+        
+        // Check if we have a valid 3D fix. The receiver keeps reporting one
+        // for a while after losing satellites, so the fix is only trusted
+        // with enough satellites to actually solve for it
+        let reports_fix = fix_type >= 3 && (flags & 0x01) != 0;
+        let coasting = reports_fix && num_sv < NAV_PVT_MIN_FIX_SATELLITES;
+        
+        Some(GpsData {
+            valid: reports_fix && !coasting,
+            itow_ms: itow,
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            nano,
+            validity,
+            latitude,
+            longitude,
+            height_msl: h_msl,
+            height_ellipsoid_mm: height,
+            horizontal_accuracy: h_acc,
+            vertical_accuracy: v_acc,
+            ground_speed: g_speed,
+            heading_motion: head_mot,
+            speed_accuracy_mms: s_acc,
+            heading_accuracy_1e5_deg: head_acc,
+            velocity_north: vel_n,
+            velocity_east: vel_e,
+            velocity_down: vel_d,
+            satellites: num_sv,
+            fix_type,
+            dead_reckoned: false,
+            coasting,
+        })
+    }
+
+    fn parse_nav_timeutc(&self) -> Option<UtcTime> {
+        if self.message.length < NAV_TIMEUTC_LENGTH {
+            return None;
+        }
+
+        let payload = &self.message.payload;
+
+        Some(UtcTime {
+            itow: u32::from_le_bytes([payload[0], payload[1], payload[2], payload[3]]),
+            time_accuracy: u32::from_le_bytes([payload[4], payload[5], payload[6], payload[7]]),
+            nano: i32::from_le_bytes([payload[8], payload[9], payload[10], payload[11]]),
+            year: u16::from_le_bytes([payload[12], payload[13]]),
+            month: payload[14],
+            day: payload[15],
+            hour: payload[16],
+            minute: payload[17],
+            second: payload[18],
+            valid: TimeUtcValid::from_bits(payload[19]),
+        })
+    }
+}
+
+impl<const N: usize> Default for UbxParser<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Compute the UBX Fletcher checksum over class, id, length and payload bytes
+fn ubx_checksum(bytes: &[u8]) -> (u8, u8) {
+    let mut ck_a: u8 = 0;
+    let mut ck_b: u8 = 0;
+    for &byte in bytes {
+        ck_a = ck_a.wrapping_add(byte);
+        ck_b = ck_b.wrapping_add(ck_a);
+    }
+    (ck_a, ck_b)
+}
+
+/// Frame a UBX message: sync chars, class, id, length, payload and checksum
+/// `L` must equal the payload length plus 8 bytes of framing
+fn build_frame<const L: usize>(class: u8, id: u8, payload: &[u8]) -> [u8; L] {
+    debug_assert_eq!(L, payload.len() + 8);
+
+    let mut frame = [0u8; L];
+    let length = payload.len() as u16;
+    frame[0..6].copy_from_slice(&[
+        UBX_SYNC_CHAR_1,
+        UBX_SYNC_CHAR_2,
+        class,
+        id,
+        length as u8,
+        (length >> 8) as u8,
+    ]);
+    frame[6..L - 2].copy_from_slice(payload);
+
+    let (ck_a, ck_b) = ubx_checksum(&frame[2..L - 2]);
+    frame[L - 2] = ck_a;
+    frame[L - 1] = ck_b;
+    frame
+}
+
+// UBX Configuration Commands
+pub struct UbxConfig;
+
+impl UbxConfig {
+    /// Get UBX command to configure port for UBX-only output (disables NMEA)
+    /// UART1 at 38400 baud, 8N1
+    pub fn get_port_config_ubx_only() -> [u8; 28] {
+        Self::build_cfg_prt_mode(0x01, UBX_UART_MODE_LEGACY, 38_400, PROTO_UBX, PROTO_UBX)
+    }
+
+    /// Build a CFG-PRT command for a UART port at 8N1
+    /// `in_proto`/`out_proto` are masks of PROTO_UBX, PROTO_NMEA and PROTO_RTCM3
+    pub fn build_cfg_prt(port: u8, baud: u32, in_proto: u16, out_proto: u16) -> [u8; 28] {
+        Self::build_cfg_prt_mode(port, UBX_UART_MODE_8N1, baud, in_proto, out_proto)
+    }
+
+    // CFG-PRT with an explicit UART mode field
+    fn build_cfg_prt_mode(port: u8, mode: u32, baud: u32, in_proto: u16, out_proto: u16) -> [u8; 28] {
+        let mut payload = [0u8; 20];
+        payload[0] = port;
+        // Bytes 1-3: reserved and TX ready pin config, left disabled
+        payload[4..8].copy_from_slice(&mode.to_le_bytes());
+        payload[8..12].copy_from_slice(&baud.to_le_bytes());
+        payload[12..14].copy_from_slice(&in_proto.to_le_bytes());
+        payload[14..16].copy_from_slice(&out_proto.to_le_bytes());
+        // Bytes 16-19: flags and reserved
+
+        build_frame(UbxClass::Cfg.into(), UBX_CFG_PRT, &payload)
+    }
+
+    /// Build a CFG-PRT poll for one port's settings
+    /// The receiver replies with a full CFG-PRT, reported as UbxEvent::CfgPrt
+    pub fn build_cfg_prt_poll(port: u8) -> [u8; 9] {
+        build_frame(UbxClass::Cfg.into(), UBX_CFG_PRT, &[port])
+    }
+
+    /// Build a CFG-PRT command that switches a UART port to a new baud rate
+    /// Keeps the port at 8N1 with UBX-only input/output
+    pub fn build_cfg_prt_baud(port: u8, baud: u32) -> [u8; 28] {
+        Self::build_cfg_prt(port, baud, PROTO_UBX, PROTO_UBX)
+    }
+
+    /// Build a CFG-CFG command that saves the current configuration
+    ///
+    /// Saves the port, message, INF, navigation and receiver manager sections
+    /// (`saveMask` = 0x1F) to every storage device present (`deviceMask` =
+    /// BBR | Flash | EEPROM | SPI flash = 0x17), so the settings survive a
+    /// power cycle. Send it after the configuration commands it should keep.
+    pub fn build_cfg_cfg_save() -> [u8; 21] {
+        Self::build_cfg_cfg(0, CFG_SAVE_SECTIONS, 0)
+    }
+
+    /// Build a CFG-CFG command that reloads the saved configuration
+    /// Uses the same section and device masks as `build_cfg_cfg_save`
+    pub fn build_cfg_cfg_load() -> [u8; 21] {
+        Self::build_cfg_cfg(0, 0, CFG_SAVE_SECTIONS)
+    }
+
+    fn build_cfg_cfg(clear_mask: u32, save_mask: u32, load_mask: u32) -> [u8; 21] {
+        let mut payload = [0u8; 13];
+        payload[0..4].copy_from_slice(&clear_mask.to_le_bytes());
+        payload[4..8].copy_from_slice(&save_mask.to_le_bytes());
+        payload[8..12].copy_from_slice(&load_mask.to_le_bytes());
+        payload[12] = CFG_SAVE_DEVICES;
+
+        build_frame(UbxClass::Cfg.into(), UBX_CFG_CFG, &payload)
+    }
+
+    /// Build a CFG-RST command
+    ///
+    /// `nav_bbr_mask` selects the battery-backed data to clear (see the
+    /// NAV_BBR_* presets) and `reset_mode` how to restart (RESET_MODE_*);
+    /// `build_cfg_rst(NAV_BBR_COLD_START, RESET_MODE_SW)` is a cold start.
+    /// The receiver does not ACK a reset, so don't wait for one; expect
+    /// output to stop briefly and the saved configuration to be reloaded.
+    pub fn build_cfg_rst(nav_bbr_mask: u16, reset_mode: u8) -> [u8; 12] {
+        let mut payload = [0u8; 4];
+        payload[0..2].copy_from_slice(&nav_bbr_mask.to_le_bytes());
+        payload[2] = reset_mode;
+        // Byte 3: reserved
+
+        build_frame(UbxClass::Cfg.into(), UBX_CFG_RST, &payload)
+    }
+
+    /// Build a CFG-VALSET command setting a single configuration key
+    ///
+    /// The value is given little-endian and its length must match the storage
+    /// size encoded in bits 28-30 of the key (1 byte for L/U1/I1/E1/X1 keys,
+    /// 2, 4 or 8 bytes for the wider types); returns None otherwise.
+    pub fn build_cfg_valset(layer: u8, key: u32, value: &[u8]) -> Option<heapless::Vec<u8, 32>> {
+        let expected_len = match (key >> 28) & 0x07 {
+            0x01 | 0x02 => 1, // One bit (sent as a byte) or one byte
+            0x03 => 2,
+            0x04 => 4,
+            0x05 => 8,
+            _ => return None,
+        };
+        if value.len() != expected_len {
+            return None;
+        }
+
+        let length = (8 + value.len()) as u16;
+        let mut frame = heapless::Vec::new();
+        frame.extend_from_slice(&[UBX_SYNC_CHAR_1, UBX_SYNC_CHAR_2, UbxClass::Cfg.into(), UBX_CFG_VALSET]).ok()?;
+        frame.extend_from_slice(&length.to_le_bytes()).ok()?;
+        frame.extend_from_slice(&[0x00, layer, 0x00, 0x00]).ok()?; // Version 0, layers, reserved
+        frame.extend_from_slice(&key.to_le_bytes()).ok()?;
+        frame.extend_from_slice(value).ok()?;
+
+        let (ck_a, ck_b) = ubx_checksum(&frame[2..]);
+        frame.extend_from_slice(&[ck_a, ck_b]).ok()?;
+        Some(frame)
+    }
+
+    /// Build a CFG-MSG command setting the output rate of a message on the current port
+    /// A rate of 1 outputs the message with every navigation solution, 0 disables it
+    pub fn build_cfg_msg(class: u8, id: u8, rate: u8) -> [u8; 11] {
+        build_frame(UbxClass::Cfg.into(), UBX_CFG_MSG, &[class, id, rate])
+    }
+
+    /// Send a CFG-MSG for each (class, id, rate) tuple, in order
+    /// Returns the number of commands sent, stopping at the first write error
+    pub fn enable_messages<W>(msgs: &[(u8, u8, u8)], tx: &mut W) -> Result<usize, W::Error>
+    where
+        W: embedded_hal_nb::serial::Write<u8>,
+    {
+        for &(class, id, rate) in msgs {
+            for byte in Self::build_cfg_msg(class, id, rate) {
+                nb::block!(tx.write(byte))?;
+            }
+        }
+        nb::block!(tx.flush())?;
+        Ok(msgs.len())
+    }
+
+    /// Build a CFG-MSG command setting the output rate of one NMEA sentence (NMEA_* ids)
+    /// Only has an effect while NMEA output is enabled on the port, e.g. with
+    /// `build_cfg_prt(.., PROTO_UBX | PROTO_NMEA)`; rate 0 turns the sentence off
+    pub fn build_nmea_msg(msg_id: u8, rate: u8) -> [u8; 11] {
+        Self::build_cfg_msg(NMEA_CLASS_STD, msg_id, rate)
+    }
+
+    /// Get UBX command to enable NAV-TIMEUTC messages
+    pub fn get_enable_nav_timeutc() -> [u8; 11] {
+        Self::build_cfg_msg(UbxClass::Nav.into(), NavId::TimeUtc.into(), 1)
+    }
+
+    /// Get UBX command to enable NAV-PVT messages
+    pub fn get_enable_nav_pvt() -> [u8; 11] {
+        let cmd = [
+            0xB5, 0x62,  // UBX sync chars
+            0x06, 0x01,  // Class CFG, ID MSG
+            0x03, 0x00,  // Length (3 bytes)
+            0x01, 0x07,  // Message Class/ID (NAV-PVT)
+            0x01,        // Rate (1 = output every solution)
+            0x13, 0x51   // Checksum
+        ];
+        debug_assert!(Self::verify(&cmd));
+        cmd
+    }
+
+    /// Build a poll request (empty payload) for any message class/id
+    /// The receiver answers with one instance of that message on the current port
+    pub fn build_poll(class: u8, id: u8) -> [u8; 8] {
+        build_frame(class, id, &[])
+    }
+
+    /// Get UBX command to poll the receiver/software version (MON-VER)
+    pub fn get_poll_mon_ver() -> [u8; 8] {
+        let cmd = [
+            0xB5, 0x62,  // UBX sync chars
+            0x0A, 0x04,  // Class MON, ID VER
+            0x00, 0x00,  // Length (0 bytes = poll request)
+            0x0E, 0x34   // Checksum
+        ];
+        debug_assert!(Self::verify(&cmd));
+        cmd
+    }
+
+    /// Check a complete UBX frame: sync chars, length field and checksum
+    ///
+    /// Recomputes the Fletcher checksum over class, id, length and payload
+    /// and compares it with the trailing two bytes. Meant for catching typos
+    /// in hand-written frames via `debug_assert!`.
+    pub fn verify(cmd: &[u8]) -> bool {
+        if cmd.len() < 8 || cmd[0] != UBX_SYNC_CHAR_1 || cmd[1] != UBX_SYNC_CHAR_2 {
+            return false;
+        }
+
+        let length = u16::from_le_bytes([cmd[4], cmd[5]]) as usize;
+        if cmd.len() != length + 8 {
+            return false;
+        }
+
+        let (ck_a, ck_b) = ubx_checksum(&cmd[2..cmd.len() - 2]);
+        cmd[cmd.len() - 2..] == [ck_a, ck_b]
+    }
+}
+
+// GPS Manager - handles all GPS operations
+pub struct GpsManager {
+    parser: UbxParser,
+    last_data: GpsData,
+}
+
+impl GpsManager {
+    pub fn new() -> Self {
+        Self {
+            parser: UbxParser::new(),
+            last_data: GpsData::new(),
+        }
+    }
+
+    /// Get UBX configuration commands 
+    /// Returns the port config and PVT enable commands
+    pub fn get_config_commands(&self) -> ([u8; 28], [u8; 11]) {
+        sensor_log!("Preparing UBX configuration commands...");
+        
+        let ubx_cfg_port = UbxConfig::get_port_config_ubx_only();
+        let ubx_cfg_pvt = UbxConfig::get_enable_nav_pvt();
+        
+        (ubx_cfg_port, ubx_cfg_pvt)
+    }
+
+    /// Update GPS data by reading a single byte
+    /// Returns Some(GpsData) if new GPS data is available
+    pub fn process_byte(&mut self, byte: u8) -> Option<GpsData> {
+        if let Some(UbxEvent::NavPvt(gps_data)) = self.parser.parse_byte(byte) {
+            self.last_data = gps_data;
+            return Some(gps_data);
+        }
+        None
+    }
+
+    /// Get the last received GPS data
+    pub fn last_data(&self) -> &GpsData {
+        &self.last_data
+    }
+
+    /// Check if GPS has a valid fix
+    pub fn has_fix(&self) -> bool {
+        self.last_data.valid
+    }
+
+    /// Get number of satellites
+    pub fn satellite_count(&self) -> u8 {
+        self.last_data.satellites
+    }
+}
+
+impl Default for GpsManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Failures of send_and_wait_ack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigError<E> {
+    Write(E),       // The command could not be sent
+    Nak,            // The receiver rejected the command
+    Timeout,        // No ACK/NAK for the command before the timeout
+    InvalidCommand, // `cmd` is not a complete UBX frame (see UbxConfig::verify)
+}
+
+/// Send a UBX command and block until the receiver acknowledges it
+///
+/// `cmd` must be a complete frame, otherwise nothing is sent and
+/// InvalidCommand is returned. Its class/id bytes are matched against
+/// incoming ACK-ACK/ACK-NAK replies, and replies for other commands are
+/// ignored. Every other frame (periodic NAV output) still goes through
+/// `parser`, but its events are dropped. Receive errors are skipped, since
+/// a garbled reply just ends in a timeout. Commands the receiver never
+/// acknowledges, such as CFG-RST, always time out.
+pub fn send_and_wait_ack<TX, RX, D, const N: usize>(
+    tx: &mut TX,
+    rx: &mut RX,
+    cmd: &[u8],
+    parser: &mut UbxParser<N>,
+    delay: &mut D,
+    timeout_ms: u32,
+) -> Result<(), ConfigError<TX::Error>>
+where
+    TX: embedded_hal_nb::serial::Write<u8>,
+    RX: embedded_hal_nb::serial::Read<u8>,
+    D: DelayNs,
+{
+    if !UbxConfig::verify(cmd) {
+        return Err(ConfigError::InvalidCommand);
+    }
+    let (cmd_class, cmd_id) = (cmd[2], cmd[3]);
+
+    for &byte in cmd {
+        nb::block!(tx.write(byte)).map_err(ConfigError::Write)?;
+    }
+    nb::block!(tx.flush()).map_err(ConfigError::Write)?;
+
+    let timeout_us = timeout_ms.saturating_mul(1000);
+    let mut waited_us = 0;
+    let mut bytes_seen = 0;
+    while waited_us < timeout_us && bytes_seen < ACK_MAX_BYTES {
+        match rx.read() {
+            Ok(byte) => match parser.parse_byte(byte) {
+                Some(UbxEvent::Ack { class, id }) if (class, id) == (cmd_class, cmd_id) => return Ok(()),
+                Some(UbxEvent::Nak { class, id }) if (class, id) == (cmd_class, cmd_id) => {
+                    return Err(ConfigError::Nak);
+                }
+                _ => bytes_seen += 1,
+            },
+            Err(_) => {
+                delay.delay_us(ACK_POLL_US);
+                waited_us += ACK_POLL_US;
+            }
+        }
+    }
+
+    Err(ConfigError::Timeout)
+}
+
+/// Configure the receiver for UBX NAV-PVT output, skipping the port setup
+/// when UBX is already flowing
+///
+/// Re-sending CFG-PRT restarts the receiver's UART, which drops bytes and
+/// makes a warm restart visibly glitch. So a NAV-PVT poll goes out first,
+/// and if any valid UBX frame comes back within UBX_DETECT_TIMEOUT_MS the
+/// port is left alone. Otherwise the UBX-only port config is sent. Either
+/// way NAV-PVT output is (re)enabled, and each command must be ACKed.
+pub fn ensure_configured<TX, RX, D, const N: usize>(
+    tx: &mut TX,
+    rx: &mut RX,
+    parser: &mut UbxParser<N>,
+    delay: &mut D,
+) -> Result<(), ConfigError<TX::Error>>
+where
+    TX: embedded_hal_nb::serial::Write<u8>,
+    RX: embedded_hal_nb::serial::Read<u8>,
+    D: DelayNs,
+{
+    let poll = UbxConfig::build_poll(UbxClass::Nav.into(), NavId::Pvt.into());
+    for byte in poll {
+        nb::block!(tx.write(byte)).map_err(ConfigError::Write)?;
+    }
+    nb::block!(tx.flush()).map_err(ConfigError::Write)?;
+
+    if wait_for_ubx(rx, parser, delay, UBX_DETECT_TIMEOUT_MS) {
+        sensor_log!("GPS already outputs UBX, skipping port config");
+    } else {
+        let port = UbxConfig::get_port_config_ubx_only();
+        send_and_wait_ack(tx, rx, &port, parser, delay, CONFIG_ACK_TIMEOUT_MS)?;
+    }
+
+    let pvt = UbxConfig::get_enable_nav_pvt();
+    send_and_wait_ack(tx, rx, &pvt, parser, delay, CONFIG_ACK_TIMEOUT_MS)
+}
+
+/// Feed received bytes to the parser until a valid frame completes
+/// Returns false if the line stays silent (or only carries NMEA) for timeout_ms
+fn wait_for_ubx<RX, D, const N: usize>(
+    rx: &mut RX,
+    parser: &mut UbxParser<N>,
+    delay: &mut D,
+    timeout_ms: u32,
+) -> bool
+where
+    RX: embedded_hal_nb::serial::Read<u8>,
+    D: DelayNs,
+{
+    let timeout_us = timeout_ms.saturating_mul(1000);
+    let mut waited_us = 0;
+    let mut bytes_seen = 0;
+    while waited_us < timeout_us && bytes_seen < ACK_MAX_BYTES {
+        match rx.read() {
+            Ok(byte) => {
+                if parser.parse_byte(byte).is_some() {
+                    return true;
+                }
+                bytes_seen += 1;
+            }
+            Err(_) => {
+                delay.delay_us(ACK_POLL_US);
+                waited_us += ACK_POLL_US;
+            }
+        }
+    }
+    false
+}
+
+// What GpsDiagnostics::probe heard on the line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ProbeResult {
+    UbxDetected,  // B5 62 sync seen; NMEA may be mixed in too
+    NmeaDetected, // "$G"/"$P" sentence starts but no UBX: the port is NMEA-only
+    Nothing,      // No bytes at all: wiring, power or TX/RX swapped
+    Garbage,      // Bytes or line errors without either protocol: usually a baud mismatch
+}
+
+/// Bring-up checks for the GPS link
+pub struct GpsDiagnostics;
+
+impl GpsDiagnostics {
+    /// Listen for up to `timeout_ms` and classify the incoming stream
+    ///
+    /// Returns UbxDetected as soon as a UBX sync pair arrives; anything else
+    /// needs the whole window, since a UBX+NMEA receiver may send its NMEA
+    /// sentences first. Sends nothing, so a silent receiver reports Nothing
+    /// even if it would answer a poll.
+    pub fn probe<RX, D>(rx: &mut RX, delay: &mut D, timeout_ms: u32) -> ProbeResult
+    where
+        RX: embedded_hal_nb::serial::Read<u8>,
+        D: DelayNs,
+    {
+        let timeout_us = timeout_ms.saturating_mul(1000);
+        let mut waited_us = 0;
+        let mut bytes_seen = 0;
+        let mut rx_errors = 0;
+        let mut nmea = false;
+        let mut previous = 0u8;
+
+        while waited_us < timeout_us && bytes_seen + rx_errors < ACK_MAX_BYTES {
+            match rx.read() {
+                Ok(byte) => {
+                    bytes_seen += 1;
+                    if previous == UBX_SYNC_CHAR_1 && byte == UBX_SYNC_CHAR_2 {
+                        return ProbeResult::UbxDetected;
+                    }
+                    // Talker IDs GP/GN/GL/GA/GB or proprietary PUBX
+                    if previous == b'$' && (byte == b'G' || byte == b'P') {
+                        nmea = true;
+                    }
+                    previous = byte;
+                }
+                Err(nb::Error::WouldBlock) => {
+                    delay.delay_us(ACK_POLL_US);
+                    waited_us += ACK_POLL_US;
+                }
+                Err(nb::Error::Other(_)) => rx_errors += 1,
+            }
+        }
+
+        if nmea {
+            ProbeResult::NmeaDetected
+        } else if bytes_seen == 0 && rx_errors == 0 {
+            ProbeResult::Nothing
+        } else {
+            ProbeResult::Garbage
+        }
+    }
+}
+
+/// Read one chunk from any `embedded_io::Read` and feed it to the parser
+///
+/// Saves rewriting the read loop for every HAL. Each call performs a single
+/// `read` of up to `scratch.len()` bytes (which blocks until at least one
+/// byte is available) and calls `on_event` for every event it completes, as
+/// `UbxParser::parse_all` does. Returns the number of bytes read: 0 at EOF,
+/// and also for an empty `scratch`, which is not read into at all.
+pub fn pump<R: embedded_io::Read, const N: usize>(
+    parser: &mut UbxParser<N>,
+    reader: &mut R,
+    scratch: &mut [u8],
+    on_event: impl FnMut(UbxEvent),
+) -> Result<usize, R::Error> {
+    if scratch.is_empty() {
+        return Ok(0);
+    }
+    let count = reader.read(scratch)?;
+    parser.parse_all(&scratch[..count], on_event);
+    Ok(count)
+}
+
+// Apogee detector - flags the top of a flight from successive GPS altitudes
+pub struct ApogeeDetector {
+    required_descents: u8, // Consecutive descending samples needed to trigger
+    arm_gain_mm: i32,      // Altitude gain over the first fix needed to arm
+    launch_altitude: Option<i32>,
+    peak_altitude: i32,
+    last_altitude: i32,
+    descent_count: u8,
+    armed: bool,
+    triggered: bool,
+}
+
+impl ApogeeDetector {
+    /// Create a detector that triggers after `required_descents` consecutive
+    /// descending fixes, once the altitude has risen `arm_gain_mm` above the first fix
+    pub fn new(required_descents: u8, arm_gain_mm: i32) -> Self {
+        Self {
+            required_descents,
+            arm_gain_mm,
+            launch_altitude: None,
+            peak_altitude: 0,
+            last_altitude: 0,
+            descent_count: 0,
+            armed: false,
+            triggered: false,
+        }
+    }
+
+    /// Feed the next fix
+    /// Returns true exactly once, on the sample that confirms apogee
+    pub fn update(&mut self, fix: &GpsData) -> bool {
+        if !fix.valid || self.triggered {
+            return false;
+        }
+
+        let altitude = fix.height_msl;
+        let launch_altitude = match self.launch_altitude {
+            Some(launch) => launch,
+            None => {
+                self.launch_altitude = Some(altitude);
+                self.peak_altitude = altitude;
+                self.last_altitude = altitude;
+                return false;
+            }
+        };
+
+        if altitude > self.peak_altitude {
+            self.peak_altitude = altitude;
+            self.descent_count = 0;
+            if self.peak_altitude - launch_altitude >= self.arm_gain_mm {
+                self.armed = true;
+            }
+        } else if self.armed && altitude < self.last_altitude {
+            self.descent_count = self.descent_count.saturating_add(1);
+        } else {
+            self.descent_count = 0;
+        }
+        self.last_altitude = altitude;
+
+        if self.armed && self.descent_count >= self.required_descents {
+            self.triggered = true;
+            return true;
+        }
+        false
+    }
+
+    /// Check if the arm threshold has been reached
+    pub fn is_armed(&self) -> bool {
+        self.armed
+    }
+
+    /// Check if apogee has already been detected
+    pub fn has_triggered(&self) -> bool {
+        self.triggered
+    }
+
+    /// Get the highest altitude seen in meters as f64
+    pub fn peak_altitude_meters(&self) -> f64 {
+        self.peak_altitude as f64 / 1000.0
+    }
+}
+
+// History of the last N valid fixes, oldest overwritten first
+pub struct FixHistory<const N: usize> {
+    fixes: [GpsData; N],
+    head: usize, // Index the next fix is written to
+    count: usize,
+}
+
+impl<const N: usize> FixHistory<N> {
+    pub fn new() -> Self {
+        Self {
+            fixes: [GpsData::new(); N],
+            head: 0,
+            count: 0,
+        }
+    }
+
+    /// Store a fix; invalid fixes are ignored
+    pub fn push(&mut self, fix: GpsData) {
+        if !fix.valid || N == 0 {
+            return;
+        }
+
+        self.fixes[self.head] = fix;
+        self.head = (self.head + 1) % N;
+        if self.count < N {
+            self.count += 1;
+        }
+    }
+
+    /// Get the most recent stored fix
+    pub fn latest(&self) -> Option<&GpsData> {
+        if self.count == 0 {
+            return None;
+        }
+        Some(&self.fixes[(self.head + N - 1) % N])
+    }
+
+    /// Get the oldest stored fix
+    pub fn oldest(&self) -> Option<&GpsData> {
+        self.iter().next()
+    }
+
+    /// Iterate over the stored fixes from oldest to newest
+    pub fn iter(&self) -> impl Iterator<Item = &GpsData> + '_ {
+        let start = (self.head + N - self.count) % N.max(1);
+        (0..self.count).map(move |i| &self.fixes[(start + i) % N])
+    }
+
+    /// Number of fixes currently stored
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Average speed in m/s over the stored track: path length / elapsed time
+    /// Returns None with fewer than two fixes or no elapsed time between them
+    pub fn average_speed_ms(&self) -> Option<f64> {
+        let oldest = self.oldest()?;
+        let latest = self.latest()?;
+
+        let mut elapsed = latest.time_of_day_seconds() - oldest.time_of_day_seconds();
+        if elapsed < 0.0 {
+            // Track spans midnight UTC
+            elapsed += 86_400.0;
+        }
+        if elapsed <= 0.0 {
+            return None;
+        }
+
+        let mut distance = 0.0;
+        let mut previous = oldest;
+        for fix in self.iter().skip(1) {
+            distance += previous.distance_to(fix);
+            previous = fix;
+        }
+        Some(distance / elapsed)
+    }
+}
+
+impl<const N: usize> Default for FixHistory<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Circular geofence around a fixed center point
+#[derive(Clone, Copy)]
+pub struct Geofence {
+    pub center_lat: i32, // Latitude in 1e-7 degrees
+    pub center_lon: i32, // Longitude in 1e-7 degrees
+    pub radius_m: f64,   // Radius in meters
+}
+
+impl Geofence {
+    pub fn new(center_lat: i32, center_lon: i32, radius_m: f64) -> Self {
+        Self {
+            center_lat,
+            center_lon,
+            radius_m,
+        }
+    }
+
+    /// Distance of the fix from the fence center in meters
+    /// Returns None if the fix is not valid
+    pub fn distance_from_center(&self, fix: &GpsData) -> Option<f64> {
+        if !fix.valid {
+            return None;
+        }
+        Some(haversine_distance_m(self.center_lat, self.center_lon, fix.latitude, fix.longitude))
+    }
+
+    /// Check if the fix lies inside the fence (boundary counts as inside)
+    /// Returns None if the fix is not valid
+    pub fn contains(&self, fix: &GpsData) -> Option<bool> {
+        self.distance_from_center(fix).map(|distance| distance <= self.radius_m)
+    }
+}
+
+/// CSV telemetry writer for GPS fixes
+///
+/// Works with anything implementing `core::fmt::Write`, such as the HAL's
+/// serial `Tx` half or a `heapless::String`. One line per fix:
+/// `timestamp,lat,lon,alt,speed,sats,fix_type` with the timestamp in
+/// ISO 8601 UTC, degrees for lat/lon, meters MSL and meters per second.
+pub struct CsvWriter<W: fmt::Write> {
+    writer: W,
+}
+
+impl<W: fmt::Write> CsvWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Give back the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Write the column header line
+    pub fn write_header(&mut self) -> fmt::Result {
+        writeln!(self.writer, "timestamp,lat,lon,alt,speed,sats,fix_type")
+    }
+
+    /// Write one fix as a CSV line
+    pub fn write_fix(&mut self, fix: &GpsData) -> fmt::Result {
+        writeln!(
+            self.writer,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z,{:.7},{:.7},{:.3},{:.3},{},{}",
+            fix.year, fix.month, fix.day, fix.hour, fix.minute, fix.second,
+            fix.latitude_degrees(),
+            fix.longitude_degrees(),
+            fix.altitude_meters(),
+            fix.speed_ms(),
+            fix.satellites,
+            fix.fix_type,
+        )
+    }
+}
+
+// Commands a GpsConfigurator can hold, and bytes per command
+pub const MAX_CONFIG_COMMANDS: usize = 8;
+pub const CONFIG_COMMAND_CAPACITY: usize = 32;
+
+// Bytes to ignore NMEA after a replay while the receiver applies the config
+pub const CONFIG_REPLAY_HOLDOFF_BYTES: u32 = 2048;
+
+/// Replays the GPS configuration when the receiver falls back to NMEA
+///
+/// After a brown-out the module restarts with its flash/default config,
+/// which is NMEA output, so the UBX parser goes silent. Call `tick` for every
+/// received byte, passing whether it looks like NMEA (e.g. a `$` that isn't
+/// part of a UBX frame); the stored commands are re-sent on the first hit.
+/// NMEA already queued in the receiver keeps arriving for a moment after a
+/// replay, so further hits are ignored for CONFIG_REPLAY_HOLDOFF_BYTES bytes.
+pub struct GpsConfigurator {
+    commands: heapless::Vec<heapless::Vec<u8, CONFIG_COMMAND_CAPACITY>, MAX_CONFIG_COMMANDS>,
+    holdoff: u32,
+    replay_count: u32,
+}
+
+impl GpsConfigurator {
+    pub fn new() -> Self {
+        Self {
+            commands: heapless::Vec::new(),
+            holdoff: 0,
+            replay_count: 0,
+        }
+    }
+
+    /// Append a complete UBX frame to the replay sequence
+    /// Returns false if the list is full or the frame is longer than CONFIG_COMMAND_CAPACITY
+    pub fn add_command(&mut self, frame: &[u8]) -> bool {
+        match heapless::Vec::from_slice(frame) {
+            Ok(command) => self.commands.push(command).is_ok(),
+            Err(()) => false,
+        }
+    }
+
+    /// Process one received byte, replaying the config if it signals a restart
+    /// Returns true if the commands were re-sent
+    pub fn tick<W>(&mut self, rx_byte_saw_nmea: bool, tx: &mut W) -> Result<bool, W::Error>
+    where
+        W: embedded_hal_nb::serial::Write<u8>,
+    {
+        if self.holdoff > 0 {
+            self.holdoff -= 1;
+            return Ok(false);
+        }
+        if !rx_byte_saw_nmea {
+            return Ok(false);
+        }
+
+        sensor_log!("GPS NMEA output detected, replaying configuration");
+        self.replay(tx)?;
+        self.holdoff = CONFIG_REPLAY_HOLDOFF_BYTES;
+        self.replay_count = self.replay_count.wrapping_add(1);
+        Ok(true)
+    }
+
+    /// Send all stored commands in order
+    pub fn replay<W>(&mut self, tx: &mut W) -> Result<(), W::Error>
+    where
+        W: embedded_hal_nb::serial::Write<u8>,
+    {
+        for command in self.commands.iter() {
+            for &byte in command.iter() {
+                nb::block!(tx.write(byte))?;
+            }
+        }
+        nb::block!(tx.flush())
+    }
+
+    /// Number of replays triggered by NMEA detection
+    pub fn replay_count(&self) -> u32 {
+        self.replay_count
+    }
+}
+
+impl Default for GpsConfigurator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Speed and course derived from successive positions
+///
+/// For receiver configurations that output position without velocity.
+/// Each valid fix is paired with the previous valid one. A fix whose
+/// timestamp doesn't move forward (zero or backwards delta, e.g. after the
+/// clock source was reset) clears the estimate and becomes the new
+/// reference; invalid fixes are ignored entirely. Position noise dominates
+/// at low speed, so prefer the NAV-PVT velocity whenever it is available.
+pub struct VelocityEstimator {
+    last: Option<(i32, i32, u32)>, // Previous valid latitude, longitude (1e-7 deg) and timestamp (ms)
+    speed_ms: Option<f64>,
+    course_deg: Option<f64>,
+}
+
+impl VelocityEstimator {
+    pub fn new() -> Self {
+        Self {
+            last: None,
+            speed_ms: None,
+            course_deg: None,
+        }
+    }
+
+    /// Add a fix taken at `timestamp_ms`
+    pub fn update(&mut self, fix: &GpsData, timestamp_ms: u32) {
+        if !fix.valid {
+            return;
+        }
+
+        if let Some((lat, lon, t)) = self.last {
+            let dt_ms = timestamp_ms.wrapping_sub(t);
+            if dt_ms != 0 && dt_ms <= u32::MAX / 2 {
+                let distance = haversine_distance_m(lat, lon, fix.latitude, fix.longitude);
+                self.speed_ms = Some(distance / (dt_ms as f64 / 1000.0));
+                // Bearing of a zero-length move is undefined, keep the last one
+                if distance > 0.0 {
+                    self.course_deg = Some(initial_bearing_deg(lat, lon, fix.latitude, fix.longitude));
+                }
+            } else {
+                // Reseeding: the previous estimate no longer pairs with the reference
+                self.speed_ms = None;
+                self.course_deg = None;
+            }
+        }
+
+        self.last = Some((fix.latitude, fix.longitude, timestamp_ms));
+    }
+
+    /// Speed between the last two valid fixes in m/s
+    pub fn speed_ms(&self) -> Option<f64> {
+        self.speed_ms
+    }
+
+    /// Course between the last two valid fixes in degrees (0-360)
+    pub fn course_deg(&self) -> Option<f64> {
+        self.course_deg
+    }
+}
+
+impl Default for VelocityEstimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Satellite threshold crossings reported by SatelliteMonitor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SatEvent {
+    Gained, // Count rose to the threshold or above
+    Lost,   // Count fell below the threshold
+}
+
+// Edge detector for the satellite count crossing a usable threshold
+pub struct SatelliteMonitor {
+    min_satellites: u8,
+    last_ok: bool,
+}
+
+impl SatelliteMonitor {
+    /// Starts in the "not enough satellites" state, so the first good fix reports Gained
+    pub fn new(min_satellites: u8) -> Self {
+        Self {
+            min_satellites,
+            last_ok: false,
+        }
+    }
+
+    /// Feed a fix; returns an event when the threshold is crossed
+    /// Invalid fixes are ignored and don't change the state
+    pub fn update(&mut self, fix: &GpsData) -> Option<SatEvent> {
+        if !fix.valid {
+            return None;
+        }
+
+        let ok = fix.has_sufficient_satellites(self.min_satellites);
+        if ok == self.last_ok {
+            return None;
+        }
+
+        self.last_ok = ok;
+        Some(if ok { SatEvent::Gained } else { SatEvent::Lost })
+    }
+}
+
+// Smoothing weight of the newest interval in FixRateMeter
+pub const FIX_RATE_ALPHA: f32 = 0.2;
+
+/// NAV-PVT update rate measured against the local clock
+///
+/// Call `record_fix` with `Hardware::millis` for every accepted fix, and
+/// `tick` from the main loop so the rate drops to 0 once fixes stop for
+/// longer than the timeout. Confirms e.g. that a 10 Hz CFG-RATE took effect.
+pub struct FixRateMeter {
+    timeout_ms: u32,
+    last_fix_ms: Option<u32>,
+    instant_hz: f32,
+    smoothed_hz: f32,
+}
+
+impl FixRateMeter {
+    pub fn new(timeout_ms: u32) -> Self {
+        Self {
+            timeout_ms,
+            last_fix_ms: None,
+            instant_hz: 0.0,
+            smoothed_hz: 0.0,
+        }
+    }
+
+    /// Note a fix received at `now_ms`
+    pub fn record_fix(&mut self, now_ms: u32) {
+        if let Some(last) = self.last_fix_ms {
+            let dt_ms = now_ms.wrapping_sub(last);
+            if dt_ms > 0 {
+                self.instant_hz = 1000.0 / dt_ms as f32;
+                // Seed the average with the first interval instead of ramping up from 0
+                self.smoothed_hz = if self.smoothed_hz == 0.0 {
+                    self.instant_hz
+                } else {
+                    self.smoothed_hz + FIX_RATE_ALPHA * (self.instant_hz - self.smoothed_hz)
+                };
+            }
+        }
+        self.last_fix_ms = Some(now_ms);
+    }
+
+    /// Reset the rate to 0 if no fix arrived within the timeout
+    pub fn tick(&mut self, now_ms: u32) {
+        if self.last_fix_ms.is_some_and(|last| now_ms.wrapping_sub(last) > self.timeout_ms) {
+            self.last_fix_ms = None;
+            self.instant_hz = 0.0;
+            self.smoothed_hz = 0.0;
+        }
+    }
+
+    /// Smoothed update rate in Hz, 0 until two fixes have arrived or after a timeout
+    pub fn hz(&self) -> f32 {
+        self.smoothed_hz
+    }
+
+    /// Rate from the last two fixes alone in Hz
+    pub fn instant_hz(&self) -> f32 {
+        self.instant_hz
+    }
+}
+
+// AltitudeFilter gains in 1/65536 units: alpha 0.1 on altitude and
+// beta = alpha^2 / (2 - alpha) on vertical rate. Lower gains smooth more
+// but lag a changing climb rate
+pub const ALTITUDE_ALPHA_Q16: i64 = 6554;
+pub const ALTITUDE_BETA_Q16: i64 = 345;
+
+// Gap after which AltitudeFilter restarts from the next sample
+pub const ALTITUDE_FILTER_MAX_GAP_MS: u32 = 5000;
+
+/// Smoothed GPS altitude and vertical rate for vehicles without a barometer
+///
+/// Alpha-beta filter in integer millimetres: each sample is compared with
+/// the altitude predicted from the current rate and a fraction of the error
+/// goes into both. Fixes that are not valid are ignored, and a gap longer
+/// than ALTITUDE_FILTER_MAX_GAP_MS restarts the filter from the next fix.
+pub struct AltitudeFilter {
+    altitude_mm: i64,
+    rate_mm_s: i64,
+    last_ms: Option<u32>,
+}
+
+impl AltitudeFilter {
+    pub fn new() -> Self {
+        Self {
+            altitude_mm: 0,
+            rate_mm_s: 0,
+            last_ms: None,
+        }
+    }
+
+    /// Feed a fix received at `timestamp_ms`
+    /// Returns (altitude m, vertical rate m/s, positive up), or None if the sample was rejected
+    pub fn update(&mut self, fix: &GpsData, timestamp_ms: u32) -> Option<(f32, f32)> {
+        if !fix.valid {
+            return None;
+        }
+        let measured = fix.height_msl as i64;
+
+        let dt_ms = match self.last_ms {
+            Some(last) => timestamp_ms.wrapping_sub(last),
+            None => u32::MAX,
+        };
+        if dt_ms == 0 {
+            return None;
+        }
+        self.last_ms = Some(timestamp_ms);
+
+        if dt_ms > ALTITUDE_FILTER_MAX_GAP_MS {
+            self.altitude_mm = measured;
+            self.rate_mm_s = 0;
+        } else {
+            let dt_ms = dt_ms as i64;
+            let predicted = self.altitude_mm + self.rate_mm_s * dt_ms / 1000;
+            let residual = measured - predicted;
+            self.altitude_mm = predicted + ALTITUDE_ALPHA_Q16 * residual / 65536;
+            self.rate_mm_s += ALTITUDE_BETA_Q16 * residual * 1000 / (65536 * dt_ms);
+        }
+
+        Some((self.altitude_meters(), self.vertical_rate_ms()))
+    }
+
+    /// Smoothed altitude above mean sea level in meters
+    pub fn altitude_meters(&self) -> f32 {
+        self.altitude_mm as f32 / 1000.0
+    }
+
+    /// Smoothed vertical rate in m/s, positive when climbing
+    pub fn vertical_rate_ms(&self) -> f32 {
+        self.rate_mm_s as f32 / 1000.0
+    }
+
+    /// Forget all samples
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl Default for AltitudeFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Events a ParserCell holds between drains; further events are dropped
+pub const PARSER_CELL_EVENTS: usize = 4;
+
+/// A UbxParser shared between an interrupt handler and the main loop
+///
+/// The USART interrupt pushes bytes with `push_byte` and the main loop takes
+/// completed events with `pop_event`. Every access runs inside a critical
+/// section (`critical_section::with`), so the ISR can never interrupt the
+/// main loop half way through a parser update. On the MCU that masks
+/// interrupts (cortex-m's single-core implementation); host tests use a lock. Critical sections are kept to
+/// one byte or one event; `with` hands out the parser itself for
+/// configuration or stats, and should be kept just as short since interrupts
+/// are masked while it runs. `new` is const, so the cell can be a `static`.
+pub struct ParserCell<const N: usize = UBX_DEFAULT_PAYLOAD_SIZE> {
+    inner: Mutex<RefCell<ParserCellInner<N>>>,
+}
+
+struct ParserCellInner<const N: usize> {
+    parser: UbxParser<N>,
+    events: heapless::Deque<UbxEvent, PARSER_CELL_EVENTS>,
+    dropped: u32, // Events lost because the queue was full
+}
+
+impl<const N: usize> ParserCell<N> {
+    pub const fn new() -> Self {
+        Self {
+            inner: Mutex::new(RefCell::new(ParserCellInner {
+                parser: UbxParser::new(),
+                events: heapless::Deque::new(),
+                dropped: 0,
+            })),
+        }
+    }
+
+    /// Feed one received byte, queueing the event it completes (if any)
+    pub fn push_byte(&self, byte: u8) {
+        critical_section::with(|cs| {
+            let mut inner = self.inner.borrow(cs).borrow_mut();
+            let Some(event) = inner.parser.parse_byte(byte) else {
+                return;
+            };
+            if inner.events.push_back(event).is_err() {
+                inner.dropped = inner.dropped.wrapping_add(1);
+            }
+        })
+    }
+
+    /// Take the oldest queued event
+    pub fn pop_event(&self) -> Option<UbxEvent> {
+        critical_section::with(|cs| self.inner.borrow(cs).borrow_mut().events.pop_front())
+    }
+
+    /// Events lost because the main loop didn't drain the queue in time
+    pub fn dropped_events(&self) -> u32 {
+        critical_section::with(|cs| self.inner.borrow(cs).borrow().dropped)
+    }
+
+    /// Run `f` with exclusive access to the parser
+    pub fn with<R>(&self, f: impl FnOnce(&mut UbxParser<N>) -> R) -> R {
+        critical_section::with(|cs| f(&mut self.inner.borrow(cs).borrow_mut().parser))
+    }
+}
+
+impl<const N: usize> Default for ParserCell<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_hal_mock::eh1::delay::NoopDelay;
+    use embedded_hal_mock::eh1::serial::{Mock as SerialMock, Transaction as SerialTransaction};
+
+    // NAV-PVT frame length on M9 firmware: 92-byte payload plus framing
+    const NAV_PVT_FRAME_LEN: usize = NAV_PVT_LENGTH as usize + UBX_MIN_FRAME_LEN;
+
+    // 3D fix with 9 satellites and 1.5 m accuracy on 2024-06-01 12:30:45 UTC
+    fn nav_pvt_payload() -> [u8; NAV_PVT_LENGTH as usize] {
+        let mut payload = [0u8; NAV_PVT_LENGTH as usize];
+        payload[0..4].copy_from_slice(&123_456_000u32.to_le_bytes());
+        payload[4..6].copy_from_slice(&2024u16.to_le_bytes());
+        payload[6..11].copy_from_slice(&[6, 1, 12, 30, 45]);
+        payload[11] = 0x07; // validDate | validTime | fullyResolved
+        payload[20] = 3; // 3D fix
+        payload[21] = 0x01; // gnssFixOK
+        payload[23] = 9;
+        payload[24..28].copy_from_slice(&(-975_000_000i32).to_le_bytes());
+        payload[28..32].copy_from_slice(&297_000_000i32.to_le_bytes());
+        payload[40..44].copy_from_slice(&1_500u32.to_le_bytes());
+        payload[64..68].copy_from_slice(&9_000_000i32.to_le_bytes()); // headMot 90 deg
+        payload[68..72].copy_from_slice(&250u32.to_le_bytes()); // sAcc
+        payload[72..76].copy_from_slice(&150_000u32.to_le_bytes()); // headAcc 1.5 deg
+        payload
+    }
+
+    fn parse_frame<const N: usize>(parser: &mut UbxParser<N>, frame: &[u8]) -> Option<UbxEvent> {
+        let mut last = None;
+        parser.parse_all(frame, |event| last = Some(event));
+        last
+    }
+
+    fn parse_fix(frame: &[u8]) -> GpsData {
+        let mut parser: UbxParser = UbxParser::new();
+        match parse_frame(&mut parser, frame) {
+            Some(UbxEvent::NavPvt(fix)) => fix,
+            _ => panic!("expected a NavPvt event"),
+        }
+    }
+
+    fn nav_pvt_frame(payload: &[u8]) -> [u8; NAV_PVT_FRAME_LEN] {
+        build_frame(UbxClass::Nav.into(), NavId::Pvt.into(), payload)
+    }
+
+    // embedded_io reader handing out at most `chunk` bytes per read
+    struct ChunkedReader<'a> {
+        data: &'a [u8],
+        chunk: usize,
+    }
+
+    impl embedded_io::ErrorType for ChunkedReader<'_> {
+        type Error = core::convert::Infallible;
+    }
+
+    impl embedded_io::Read for ChunkedReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            let count = buf.len().min(self.chunk).min(self.data.len());
+            buf[..count].copy_from_slice(&self.data[..count]);
+            self.data = &self.data[count..];
+            Ok(count)
+        }
+    }
+
+    #[test]
+    fn pump_decodes_nav_pvt_delivered_in_chunks() {
+        let frame = nav_pvt_frame(&nav_pvt_payload());
+        let mut reader = ChunkedReader { data: &frame, chunk: 7 };
+        let mut parser: UbxParser = UbxParser::new();
+        let mut scratch = [0u8; 32];
+
+        let mut reads = 0;
+        let mut fix = None;
+        while fix.is_none() {
+            reads += 1;
+            pump(&mut parser, &mut reader, &mut scratch, |event| {
+                if let UbxEvent::NavPvt(data) = event {
+                    fix = Some(data);
+                }
+            })
+            .unwrap();
+        }
+        assert_eq!(reads, NAV_PVT_FRAME_LEN.div_ceil(7));
+        let fix = fix.unwrap();
+        assert!(fix.valid);
+        assert_eq!((fix.latitude, fix.longitude), (297_000_000, -975_000_000));
+
+        // EOF
+        assert!(matches!(pump(&mut parser, &mut reader, &mut scratch, |_| {}), Ok(0)));
+    }
+
+    #[test]
+    fn pump_delivers_every_frame_in_one_read() {
+        let frame = nav_pvt_frame(&nav_pvt_payload());
+        let mut stream = [0u8; 2 * NAV_PVT_FRAME_LEN];
+        stream[..NAV_PVT_FRAME_LEN].copy_from_slice(&frame);
+        stream[NAV_PVT_FRAME_LEN..].copy_from_slice(&frame);
+        let mut reader = ChunkedReader { data: &stream, chunk: stream.len() };
+        let mut parser: UbxParser = UbxParser::new();
+        let mut scratch = [0u8; 2 * NAV_PVT_FRAME_LEN];
+
+        let mut fixes = 0;
+        let count = pump(&mut parser, &mut reader, &mut scratch, |event| {
+            assert!(matches!(event, UbxEvent::NavPvt(_)), "expected only NavPvt events");
+            fixes += 1;
+        });
+        assert!(matches!(count, Ok(n) if n == stream.len()));
+        assert_eq!(fixes, 2);
+    }
+
+    #[test]
+    fn pump_with_empty_scratch_reads_nothing() {
+        let frame = nav_pvt_frame(&nav_pvt_payload());
+        let mut reader = ChunkedReader { data: &frame, chunk: 1 };
+        let mut parser: UbxParser = UbxParser::new();
+        assert!(matches!(pump(&mut parser, &mut reader, &mut [], |_| {}), Ok(0)));
+        assert_eq!(reader.data.len(), frame.len(), "no bytes should be consumed");
+    }
+
+    #[test]
+    fn feed_yields_back_to_back_frames() {
+        let frame = nav_pvt_frame(&nav_pvt_payload());
+        let mut data = [0u8; 2 * NAV_PVT_FRAME_LEN];
+        data[..NAV_PVT_FRAME_LEN].copy_from_slice(&frame);
+        data[NAV_PVT_FRAME_LEN..].copy_from_slice(&frame);
+
+        let mut parser: UbxParser = UbxParser::new();
+        let fixes = parser.feed(&data).filter(|event| matches!(event, UbxEvent::NavPvt(_))).count();
+        assert_eq!(fixes, 2);
+        assert_eq!(parser.stats().frames, 2);
+    }
+
+    #[test]
+    fn doubled_sync_char_keeps_the_frame() {
+        let frame = nav_pvt_frame(&nav_pvt_payload());
+        let mut data = [0u8; NAV_PVT_FRAME_LEN + 1];
+        data[0] = UBX_SYNC_CHAR_1; // B5 B5 62 ...
+        data[1..].copy_from_slice(&frame);
+
+        let mut parser: UbxParser = UbxParser::new();
+        assert!(matches!(parse_frame(&mut parser, &data), Some(UbxEvent::NavPvt(_))));
+        assert_eq!(parser.stats().bytes_skipped, 1);
+    }
+
+    #[test]
+    fn oversize_frame_is_dropped_by_a_small_parser() {
+        let oversize: [u8; 108] = build_frame(UbxClass::Nav.into(), NavId::Pvt.into(), &[0u8; 100]);
+        let ack: [u8; 10] = build_frame(UbxClass::Ack.into(), UBX_ACK_ACK, &[0x06, 0x00]);
+
+        let mut parser = UbxParser::<64>::new();
+        assert!(parse_frame(&mut parser, &oversize).is_none());
+        assert_eq!(parser.stats().rejected_headers, 1);
+        assert_eq!(parser.stats().frames, 0);
+
+        // The skipped payload doesn't wedge the parser
+        assert!(matches!(parse_frame(&mut parser, &ack), Some(UbxEvent::Ack { class: 0x06, id: 0x00 })));
+    }
+
+    #[test]
+    fn placeholder_date_clears_date_valid() {
+        let fix = parse_fix(&nav_pvt_frame(&nav_pvt_payload()));
+        assert!(fix.validity.date_valid);
+
+        let mut cold_start = nav_pvt_payload();
+        cold_start[4..6].copy_from_slice(&1980u16.to_le_bytes());
+        let fix = parse_fix(&nav_pvt_frame(&cold_start));
+        assert!(!fix.validity.date_valid);
+        // Only the timestamp is distrusted, the position stays usable
+        assert!(fix.valid);
+        assert_eq!(fix.latitude, 297_000_000);
+
+        let mut no_month = nav_pvt_payload();
+        no_month[6] = 0;
+        assert!(!parse_fix(&nav_pvt_frame(&no_month)).validity.date_valid);
+    }
+
+    #[test]
+    fn fix_with_too_few_satellites_is_coasting() {
+        let mut payload = nav_pvt_payload();
+        payload[23] = 6;
+        let fix = parse_fix(&nav_pvt_frame(&payload));
+        assert!(fix.valid);
+        assert!(!fix.coasting);
+
+        payload[23] = 3;
+        let fix = parse_fix(&nav_pvt_frame(&payload));
+        assert!(!fix.valid);
+        assert!(fix.coasting);
+        assert_eq!(fix.fix_type, 3);
+    }
+
+    #[test]
+    fn packet_crc_round_trip_and_bit_flip() {
+        // CRC-16/CCITT-FALSE check value
+        assert_eq!(crc16_ccitt(b"123456789"), 0x29B1);
+
+        let fix = parse_fix(&nav_pvt_frame(&nav_pvt_payload()));
+        let framed = fix.to_packet_crc();
+        let decoded = GpsData::from_packet_crc(&framed).ok().unwrap();
+        assert_eq!((decoded.latitude, decoded.longitude), (fix.latitude, fix.longitude));
+        assert!(decoded.valid);
+
+        let mut corrupted = framed;
+        corrupted[5] ^= 0x10;
+        let error = GpsData::from_packet_crc(&corrupted).err().unwrap();
+        assert_eq!(error.received, crc16_ccitt(&framed[..PACKET_LEN]));
+        assert_ne!(error.calculated, error.received);
+    }
+
+    #[test]
+    fn short_nav_payload_reports_unexpected_length() {
+        let payload = nav_pvt_payload();
+        let (class, id) = (UbxClass::Nav.into(), NavId::Pvt.into());
+        let mut parser: UbxParser = UbxParser::new();
+
+        let event = parse_frame(&mut parser, &build_frame::<56>(class, id, &payload[..48]));
+        assert!(matches!(
+            event,
+            Some(UbxEvent::UnexpectedLength { class: 0x01, id: 0x07, got: 48, expected: NAV_PVT_LENGTH })
+        ));
+
+        // Long enough to decode: only reported once strict length checking is on
+        let protocol_14: [u8; 92] = build_frame(class, id, &payload[..84]);
+        assert!(matches!(parse_frame(&mut parser, &protocol_14), Some(UbxEvent::NavPvt(_))));
+        parser.set_strict_length(true);
+        assert!(matches!(
+            parse_frame(&mut parser, &protocol_14),
+            Some(UbxEvent::UnexpectedLength { got: 84, .. })
+        ));
+    }
+
+    #[test]
+    fn nav_pvt_decodes_fields_present_in_each_length() {
+        let payload = nav_pvt_payload();
+        let (class, id) = (UbxClass::Nav.into(), NavId::Pvt.into());
+        let extras = |fix: &GpsData| (fix.heading_motion, fix.speed_accuracy_mms, fix.heading_accuracy_1e5_deg);
+
+        // Older protocol versions: core fields only
+        let fix = parse_fix(&build_frame::<72>(class, id, &payload[..64]));
+        assert!(fix.valid);
+        assert_eq!((fix.latitude, fix.horizontal_accuracy), (297_000_000, 1_500));
+        assert_eq!(extras(&fix), (0, 0, 0));
+
+        let fix = parse_fix(&build_frame::<84>(class, id, &payload[..76]));
+        assert_eq!(extras(&fix), (9_000_000, 250, 150_000));
+
+        let fix = parse_fix(&nav_pvt_frame(&payload));
+        assert_eq!(extras(&fix), (9_000_000, 250, 150_000));
+        assert_eq!(fix.speed_accuracy_ms(), 0.25);
+        assert_eq!(fix.heading_accuracy_degrees(), 1.5);
+    }
+
+    #[test]
+    fn min_accuracy_rejects_nan_and_negative() {
+        let mut parser: UbxParser = UbxParser::new();
+        assert!(parser.set_min_accuracy(2.5));
+        assert_eq!(parser.max_hacc_mm, 2_500);
+
+        assert!(!parser.set_min_accuracy(f32::NAN));
+        assert!(!parser.set_min_accuracy(-1.0));
+        assert_eq!(parser.max_hacc_mm, 2_500);
+
+        assert!(parser.set_min_accuracy(1.0e7));
+        assert_eq!(parser.max_hacc_mm, u32::MAX);
+        assert!(parser.set_min_accuracy(f32::INFINITY));
+        assert_eq!(parser.max_hacc_mm, u32::MAX);
+    }
+
+    #[test]
+    fn velocity_estimator_reseeds_on_time_going_backwards() {
+        // 0.001 deg of latitude is ~111 m
+        let start = GpsDataBuilder::new().lat(29.7).lon(-97.5).valid().build();
+        let north = GpsDataBuilder::new().lat(29.701).lon(-97.5).valid().build();
+        let further = GpsDataBuilder::new().lat(29.702).lon(-97.5).valid().build();
+        let east = GpsDataBuilder::new().lat(29.702).lon(-97.499).valid().build();
+
+        let mut estimator = VelocityEstimator::new();
+        estimator.update(&start, 40_000);
+        estimator.update(&north, 50_000);
+        assert!(estimator.speed_ms().is_some());
+
+        // Clock restarted: the estimate is dropped, and this fix is the new reference
+        estimator.update(&east, 1_000);
+        assert_eq!(estimator.speed_ms(), None);
+        assert_eq!(estimator.course_deg(), None);
+
+        estimator.update(&further, 1_000);
+        assert_eq!(estimator.speed_ms(), None);
+
+        estimator.update(&north, 11_000);
+        let speed = estimator.speed_ms().unwrap();
+        assert!((speed - 11.1).abs() < 0.1, "{}", speed);
+        assert!((estimator.course_deg().unwrap() - 180.0).abs() < 0.01);
+
+        estimator.update(&further, 21_000);
+        let speed = estimator.speed_ms().unwrap();
+        assert!((speed - 11.1).abs() < 0.1, "{}", speed);
+        assert!(estimator.course_deg().unwrap() < 0.01);
+    }
+
+    #[test]
+    fn parser_cell_hands_events_across_threads() {
+        static CELL: ParserCell = ParserCell::new();
+        let frame = nav_pvt_frame(&nav_pvt_payload());
+
+        // The "ISR" completes more frames than the queue holds before the main loop runs
+        std::thread::spawn(move || {
+            for _ in 0..PARSER_CELL_EVENTS + 2 {
+                for &byte in &frame {
+                    CELL.push_byte(byte);
+                }
+            }
+        })
+        .join()
+        .unwrap();
+
+        let mut fixes = 0;
+        while let Some(event) = CELL.pop_event() {
+            assert!(matches!(event, UbxEvent::NavPvt(_)), "expected only NavPvt events");
+            fixes += 1;
+        }
+        assert_eq!(fixes, PARSER_CELL_EVENTS);
+        assert_eq!(CELL.dropped_events(), 2);
+        assert_eq!(CELL.with(|parser| parser.stats().frames), PARSER_CELL_EVENTS as u32 + 2);
+    }
+
+    #[test]
+    fn strict_sync_skips_nmea_around_a_valid_frame() {
+        use crate::sensors::test_vectors::NMEA_NOISE;
+
+        let frame = nav_pvt_frame(&nav_pvt_payload());
+        let mut parser: UbxParser = UbxParser::new();
+        parser.set_strict_sync(true);
+
+        let mut fixes = 0;
+        let mut on_event = |event| {
+            assert!(matches!(event, UbxEvent::NavPvt(_)), "expected only NavPvt events");
+            fixes += 1;
+        };
+        parser.parse_all(NMEA_NOISE, &mut on_event);
+        // A pair split by another byte is not a sync
+        parser.parse_all(&[UBX_SYNC_CHAR_1, b'$', UBX_SYNC_CHAR_2], &mut on_event);
+        parser.parse_all(&frame, &mut on_event);
+        // A stray pair right before an NMEA sentence fails the class check at '$'
+        parser.parse_all(&[UBX_SYNC_CHAR_1, UBX_SYNC_CHAR_2], &mut on_event);
+        parser.parse_all(NMEA_NOISE, &mut on_event);
+        parser.parse_all(&frame, &mut on_event);
+
+        assert_eq!(fixes, 2);
+        let stats = parser.stats();
+        assert_eq!(stats.frames, 2);
+        assert_eq!(stats.checksum_errors, 0);
+        assert_eq!(stats.rejected_headers, 1);
+        assert_eq!(stats.bytes_skipped as usize, 2 * NMEA_NOISE.len() + 3);
+    }
+
+    // ACK-ACK (id 0x01) or ACK-NAK (id 0x00) for the command class/id
+    fn ack_frame(ack_id: u8, cmd_class: u8, cmd_id: u8) -> [u8; 10] {
+        build_frame(UbxClass::Ack.into(), ack_id, &[cmd_class, cmd_id])
+    }
+
+    // Serial traffic for sending `cmd`, followed by the replies
+    fn send_transactions(cmd: &[u8], replies: Vec<SerialTransaction<u8>>) -> Vec<SerialTransaction<u8>> {
+        let mut transactions = vec![SerialTransaction::write_many(cmd), SerialTransaction::flush()];
+        transactions.extend(replies);
+        transactions
+    }
+
+    #[test]
+    fn send_and_wait_ack_matches_the_ack_for_its_command() {
+        let cmd = UbxConfig::build_cfg_msg(0x01, 0x07, 1);
+        let mut serial = SerialMock::new(&send_transactions(&cmd, vec![
+            SerialTransaction::read_error(nb::Error::WouldBlock),
+            // ACK for a different command is ignored
+            SerialTransaction::read_many(ack_frame(0x01, 0x06, 0x00)),
+            SerialTransaction::read_many(ack_frame(0x01, 0x06, 0x01)),
+        ]));
+        let mut parser: UbxParser = UbxParser::new();
+
+        let (mut tx, mut rx) = (serial.clone(), serial.clone());
+        let result = send_and_wait_ack(&mut tx, &mut rx, &cmd, &mut parser, &mut NoopDelay, 100);
+        assert_eq!(result, Ok(()));
+        serial.done();
+    }
+
+    #[test]
+    fn send_and_wait_ack_reports_nak() {
+        let cmd = UbxConfig::build_cfg_msg(0x01, 0x07, 1);
+        let nak = ack_frame(0x00, 0x06, 0x01);
+        let mut serial = SerialMock::new(&send_transactions(&cmd, vec![SerialTransaction::read_many(nak)]));
+        let mut parser: UbxParser = UbxParser::new();
+
+        let (mut tx, mut rx) = (serial.clone(), serial.clone());
+        let result = send_and_wait_ack(&mut tx, &mut rx, &cmd, &mut parser, &mut NoopDelay, 100);
+        assert_eq!(result, Err(ConfigError::Nak));
+        serial.done();
+    }
+
+    #[test]
+    fn send_and_wait_ack_times_out_without_a_reply() {
+        let cmd = UbxConfig::build_cfg_msg(0x01, 0x07, 1);
+        // 1 ms at ACK_POLL_US per empty read
+        let idle = (0..1000 / ACK_POLL_US).map(|_| SerialTransaction::read_error(nb::Error::WouldBlock)).collect();
+        let mut serial = SerialMock::new(&send_transactions(&cmd, idle));
+        let mut parser: UbxParser = UbxParser::new();
+
+        let (mut tx, mut rx) = (serial.clone(), serial.clone());
+        let result = send_and_wait_ack(&mut tx, &mut rx, &cmd, &mut parser, &mut NoopDelay, 1);
+        assert_eq!(result, Err(ConfigError::Timeout));
+        serial.done();
+    }
+
+    #[test]
+    fn send_and_wait_ack_rejects_a_truncated_command() {
+        let mut serial = SerialMock::new(&[]);
+        let mut parser: UbxParser = UbxParser::new();
+
+        let cmd = [UBX_SYNC_CHAR_1, UBX_SYNC_CHAR_2, 0x06];
+        let (mut tx, mut rx) = (serial.clone(), serial.clone());
+        let result = send_and_wait_ack(&mut tx, &mut rx, &cmd, &mut parser, &mut NoopDelay, 100);
+        assert_eq!(result, Err(ConfigError::InvalidCommand));
+        serial.done();
+    }
+
+    #[test]
+    fn ensure_configured_skips_port_config_when_ubx_is_flowing() {
+        let poll = UbxConfig::build_poll(UbxClass::Nav.into(), NavId::Pvt.into());
+        let pvt = UbxConfig::get_enable_nav_pvt();
+        let mut transactions = send_transactions(&poll, vec![SerialTransaction::read_many(crate::sensors::test_vectors::NAV_PVT)]);
+        transactions.extend(send_transactions(&pvt, vec![SerialTransaction::read_many(ack_frame(0x01, 0x06, 0x01))]));
+        let mut serial = SerialMock::new(&transactions);
+        let mut parser: UbxParser = UbxParser::new();
+
+        // No CFG-PRT in the expected writes, so sending one fails the mock
+        let (mut tx, mut rx) = (serial.clone(), serial.clone());
+        assert_eq!(ensure_configured(&mut tx, &mut rx, &mut parser, &mut NoopDelay), Ok(()));
+        serial.done();
+    }
+
+    #[test]
+    fn ensure_configured_sends_port_config_on_a_silent_line() {
+        let poll = UbxConfig::build_poll(UbxClass::Nav.into(), NavId::Pvt.into());
+        let port = UbxConfig::get_port_config_ubx_only();
+        let pvt = UbxConfig::get_enable_nav_pvt();
+        // The whole detect window at ACK_POLL_US per empty read
+        let idle = (0..UBX_DETECT_TIMEOUT_MS * 1000 / ACK_POLL_US)
+            .map(|_| SerialTransaction::read_error(nb::Error::WouldBlock))
+            .collect();
+        let mut transactions = send_transactions(&poll, idle);
+        transactions.extend(send_transactions(&port, vec![SerialTransaction::read_many(ack_frame(0x01, 0x06, 0x00))]));
+        transactions.extend(send_transactions(&pvt, vec![SerialTransaction::read_many(ack_frame(0x01, 0x06, 0x01))]));
+        let mut serial = SerialMock::new(&transactions);
+        let mut parser: UbxParser = UbxParser::new();
+
+        let (mut tx, mut rx) = (serial.clone(), serial.clone());
+        assert_eq!(ensure_configured(&mut tx, &mut rx, &mut parser, &mut NoopDelay), Ok(()));
+        serial.done();
+    }
+
+    #[test]
+    fn port_config_wrapper_keeps_the_original_bytes() {
+        let expected = [
+            0xB5, 0x62, 0x06, 0x00, 0x14, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x23, 0x00, 0x23,
+            0x00, 0x96, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0xF9, 0xB0,
+        ];
+        assert_eq!(UbxConfig::get_port_config_ubx_only(), expected);
+        assert!(UbxConfig::verify(&expected));
+
+        // Only the protocol masks change with the arguments
+        let hybrid = UbxConfig::build_cfg_prt(0x01, 38_400, PROTO_UBX, PROTO_UBX | PROTO_NMEA);
+        assert_eq!(&hybrid[18..22], &[0x01, 0x00, 0x03, 0x00]);
+        assert!(UbxConfig::verify(&hybrid));
+    }
+
+    #[test]
+    fn display_writes_the_fix_summary_to_any_writer() {
+        use core::fmt::Write;
+
+        let fix = parse_fix(&nav_pvt_frame(&nav_pvt_payload()));
+        let mut out: heapless::String<160> = heapless::String::new();
+        write!(out, "{}", fix).unwrap();
+        assert_eq!(
+            out.as_str(),
+            "GPS Fix: 2024/06/01 12:30:45\n\
+             Position: 29.7000000°, -97.5000000° (±1.5m)\n\
+             Altitude: 0.0m, Speed: 0.0m/s, Sats: 9"
+        );
+
+        out.clear();
+        write!(out, "{}", GpsData::new()).unwrap();
+        assert_eq!(out.as_str(), "GPS: No valid fix");
+    }
+
+    #[test]
+    fn apogee_detector_fires_once_after_a_synthetic_flight() {
+        let at = |meters| GpsDataBuilder::new().alt(meters).valid().build();
+        let mut detector = ApogeeDetector::new(3, 50_000);
+
+        // Pad jitter, including three drops in a row, stays below the arm gain
+        for meters in [100.0, 101.0, 100.5, 100.2, 99.9, 100.4] {
+            assert!(!detector.update(&at(meters)));
+        }
+        assert!(!detector.is_armed());
+
+        // Ascent arms the detector; a short dip near the top doesn't trigger
+        for meters in [120.0, 160.0, 210.0, 260.0, 255.0, 290.0, 300.0] {
+            assert!(!detector.update(&at(meters)));
+        }
+        assert!(detector.is_armed());
+
+        // Descent: an invalid fix in between is ignored, the third drop triggers
+        assert!(!detector.update(&at(298.0)));
+        assert!(!detector.update(&GpsData::new()));
+        assert!(!detector.update(&at(294.0)));
+        assert!(detector.update(&at(288.0)));
+        assert!(detector.has_triggered());
+        assert_eq!(detector.peak_altitude_meters(), 300.0);
+
+        // Only once
+        assert!(!detector.update(&at(250.0)));
+    }
+
+    #[test]
+    fn geofence_boundary_and_invalid_fix() {
+        let fence = Geofence::new(297_000_000, -975_000_000, 100.0);
+        let north_by = |lat_1e7: i32| GpsData {
+            valid: true,
+            latitude: 297_000_000 + lat_1e7,
+            longitude: -975_000_000,
+            ..GpsData::new()
+        };
+
+        // One meter of latitude is about 90 units of 1e-7 degrees
+        let inside = north_by(8_990);
+        let outside = north_by(8_996);
+        let inside_m = fence.distance_from_center(&inside).unwrap();
+        let outside_m = fence.distance_from_center(&outside).unwrap();
+        assert!(inside_m > 99.9 && inside_m < 100.0, "{}", inside_m);
+        assert!(outside_m > 100.0 && outside_m < 100.1, "{}", outside_m);
+        assert_eq!(fence.contains(&inside), Some(true));
+        assert_eq!(fence.contains(&outside), Some(false));
+
+        let invalid = GpsData { valid: false, ..inside };
+        assert_eq!(fence.contains(&invalid), None);
+        assert_eq!(fence.distance_from_center(&invalid), None);
+    }
+
+    #[test]
+    fn cfg_prt_baud_frame_for_115200() {
+        let expected = [
+            0xB5, 0x62, 0x06, 0x00, 0x14, 0x00, 0x01, 0x00, 0x00, 0x00, 0xD0, 0x08, 0x00, 0x00,
+            0x00, 0xC2, 0x01, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0xB8, 0x42,
+        ];
+        assert_eq!(UbxConfig::build_cfg_prt_baud(0x01, 115_200), expected);
+    }
+
+    #[test]
+    fn fix_history_wraps_around_and_keeps_the_newest() {
+        // One fix a second, 0.001 deg (~111 m) further north each time
+        let fix_at = |second: u8| GpsData {
+            second,
+            ..GpsDataBuilder::new().lat(29.7 + 0.001 * second as f64).lon(-97.5).valid().build()
+        };
+
+        let mut history: FixHistory<3> = FixHistory::new();
+        assert!(history.is_empty() && history.latest().is_none() && history.average_speed_ms().is_none());
+        for second in 0..5 {
+            history.push(fix_at(second));
+            history.push(GpsData::new()); // Invalid, not stored
+        }
+
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.oldest().map(|fix| fix.second), Some(2));
+        assert_eq!(history.latest().map(|fix| fix.second), Some(4));
+        let seconds: Vec<u8> = history.iter().map(|fix| fix.second).collect();
+        assert_eq!(seconds, [2, 3, 4]);
+
+        let speed = history.average_speed_ms().unwrap();
+        assert!((speed - 111.2).abs() < 0.1, "{}", speed);
+    }
+
+    #[test]
+    fn climb_rate_is_positive_when_ascending() {
+        let fix = GpsDataBuilder::new().valid().build();
+        assert_eq!(GpsData { velocity_down: -2_000, ..fix }.climb_rate_ms(), 2.0);
+        assert_eq!(GpsData { velocity_down: 1_500, ..fix }.climb_rate_ms(), -1.5);
+        assert!(GpsData { velocity_down: 1_500, ..GpsData::new() }.climb_rate_ms().is_nan());
+    }
+
+    #[test]
+    fn cfg_cfg_save_and_load_frames() {
+        // clearMask, saveMask, loadMask, deviceMask
+        let save = [
+            0xB5, 0x62, 0x06, 0x09, 0x0D, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x17, 0x52, 0xE7,
+        ];
+        let load = [
+            0xB5, 0x62, 0x06, 0x09, 0x0D, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x1F, 0x00, 0x00, 0x00, 0x17, 0x52, 0x6B,
+        ];
+        assert_eq!(UbxConfig::build_cfg_cfg_save(), save);
+        assert_eq!(UbxConfig::build_cfg_cfg_load(), load);
+    }
+
+    #[test]
+    fn course_over_ground_follows_velocity_above_the_floor() {
+        let moving = |north: i32, east: i32, speed: i32| GpsData {
+            velocity_north: north,
+            velocity_east: east,
+            ground_speed: speed,
+            ..GpsDataBuilder::new().valid().build()
+        };
+
+        assert_eq!(moving(0, 1_000, 1_000).course_over_ground(), Some(90.0));
+        assert_eq!(moving(-1_000, -1_000, 1_414).course_over_ground(), Some(225.0));
+        assert_eq!(moving(2_000, 0, 2_000).course_over_ground(), Some(0.0));
+
+        // Walking pace is below COURSE_MIN_SPEED_MMS unless the floor is lowered
+        let slow = moving(0, -300, 300);
+        assert_eq!(slow.course_over_ground(), None);
+        assert_eq!(slow.course_over_ground_above(200), Some(270.0));
+
+        assert_eq!(GpsData { valid: false, ..moving(0, 1_000, 1_000) }.course_over_ground(), None);
+    }
+
+    #[test]
+    fn time_is_valid_needs_date_time_and_fully_resolved() {
+        // Every combination of the four `valid` bits, without a position fix
+        for bits in 0..16u8 {
+            let fix = GpsData { validity: ValidFlags::from_bits(bits), ..GpsData::new() };
+            assert_eq!(fix.time_is_valid(), bits & 0x07 == 0x07, "valid = {:#06b}", bits);
+        }
+    }
+
+    #[test]
+    fn cfg_valset_enables_nav_pvt_on_uart1() {
+        let frame = UbxConfig::build_cfg_valset(VALSET_LAYER_RAM, CFG_MSGOUT_UBX_NAV_PVT_UART1, &[1]).unwrap();
+        let expected = [
+            0xB5, 0x62, 0x06, 0x8A, 0x09, 0x00, 0x00, 0x01, 0x00, 0x00, 0x07, 0x00, 0x91, 0x20, 0x01, 0x53, 0x48,
+        ];
+        assert_eq!(frame.as_slice(), expected);
+
+        // A U1 key takes exactly one value byte
+        assert!(UbxConfig::build_cfg_valset(VALSET_LAYER_RAM, CFG_MSGOUT_UBX_NAV_PVT_UART1, &[1, 0]).is_none());
+    }
+
+    #[test]
+    fn raw_capture_round_trips_an_unknown_class() {
+        let payload = [0xDE, 0xAD, 0xBE, 0xEF, 0x01];
+        let frame: [u8; 13] = build_frame(0x42, 0x01, &payload);
+        let mut parser: UbxParser = UbxParser::new();
+
+        assert!(matches!(parse_frame(&mut parser, &frame), Some(UbxEvent::Other { class: 0x42, id: 0x01 })));
+
+        parser.set_raw_capture(true);
+        let Some(UbxEvent::Raw(raw)) = parse_frame(&mut parser, &frame) else {
+            panic!("expected a Raw event");
+        };
+        assert_eq!((raw.class, raw.id, raw.length), (0x42, 0x01, 5));
+        assert_eq!(raw.payload(), payload);
+        assert!(!raw.is_truncated());
+
+        // Longer payloads keep the first RAW_PAYLOAD_CAPACITY bytes
+        let long: [u8; RAW_PAYLOAD_CAPACITY + 2] = core::array::from_fn(|i| i as u8);
+        let frame: [u8; RAW_PAYLOAD_CAPACITY + 10] = build_frame(0x42, 0x02, &long);
+        let Some(UbxEvent::Raw(raw)) = parse_frame(&mut parser, &frame) else {
+            panic!("expected a Raw event");
+        };
+        assert!(raw.is_truncated());
+        assert_eq!(raw.payload(), &long[..RAW_PAYLOAD_CAPACITY]);
+    }
+
+    #[test]
+    fn nav_timeutc_decodes_time_and_validity() {
+        // Synthetic payload: 2024-06-01 12:30:44.99988 UTC from USNO, 25 ns accuracy
+        let mut payload = [0u8; NAV_TIMEUTC_LENGTH as usize];
+        payload[0..4].copy_from_slice(&123_444_000u32.to_le_bytes());
+        payload[4..8].copy_from_slice(&25u32.to_le_bytes());
+        payload[8..12].copy_from_slice(&(-120_000i32).to_le_bytes());
+        payload[12..14].copy_from_slice(&2024u16.to_le_bytes());
+        payload[14..19].copy_from_slice(&[6, 1, 12, 30, 45]);
+        payload[19] = 0x37; // validTOW | validWKN | validUTC, utcStandard 3
+        let frame: [u8; NAV_TIMEUTC_LENGTH as usize + UBX_MIN_FRAME_LEN] =
+            build_frame(UbxClass::Nav.into(), NavId::TimeUtc.into(), &payload);
+
+        let mut parser: UbxParser = UbxParser::new();
+        let Some(UbxEvent::NavTimeUtc(time)) = parse_frame(&mut parser, &frame) else {
+            panic!("expected a NavTimeUtc event");
+        };
+        assert_eq!((time.itow, time.time_accuracy, time.nano), (123_444_000, 25, -120_000));
+        assert_eq!((time.year, time.month, time.day), (2024, 6, 1));
+        assert_eq!((time.hour, time.minute, time.second), (12, 30, 45));
+        assert!(time.valid.tow_valid && time.valid.week_valid && time.valid.utc_valid);
+        assert_eq!(time.valid.utc_standard, 3);
+
+        let enable = UbxConfig::get_enable_nav_timeutc();
+        assert_eq!(&enable[2..9], &[0x06, 0x01, 0x03, 0x00, 0x01, 0x21, 0x01]);
+    }
+
+    #[test]
+    fn on_message_dispatches_custom_and_built_in_messages() {
+        use core::sync::atomic::{AtomicU32, Ordering};
+
+        static CUSTOM_BYTES: AtomicU32 = AtomicU32::new(0);
+        static PVT_CALLS: AtomicU32 = AtomicU32::new(0);
+        fn on_custom(payload: &[u8]) {
+            CUSTOM_BYTES.fetch_add(payload.iter().map(|&b| b as u32).sum(), Ordering::Relaxed);
+        }
+        fn on_pvt(_payload: &[u8]) {
+            PVT_CALLS.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let mut parser: UbxParser = UbxParser::new();
+        assert!(parser.on_message(0x42, 0x10, on_custom));
+        assert!(parser.on_message(UbxClass::Nav.into(), NavId::Pvt.into(), on_pvt));
+
+        let custom: [u8; 11] = build_frame(0x42, 0x10, &[1, 2, 3]);
+        let other_id: [u8; 11] = build_frame(0x42, 0x11, &[100, 100, 100]);
+        assert!(matches!(parse_frame(&mut parser, &custom), Some(UbxEvent::Other { class: 0x42, id: 0x10 })));
+        parse_frame(&mut parser, &other_id);
+        assert_eq!(CUSTOM_BYTES.load(Ordering::Relaxed), 6);
+
+        // The built-in decode still runs after the handler
+        assert!(matches!(parse_frame(&mut parser, &nav_pvt_frame(&nav_pvt_payload())), Some(UbxEvent::NavPvt(_))));
+        assert_eq!(PVT_CALLS.load(Ordering::Relaxed), 1);
+
+        // Fixed-size table
+        for _ in 2..MAX_MESSAGE_HANDLERS {
+            assert!(parser.on_message(0x42, 0x20, on_custom));
+        }
+        assert!(!parser.on_message(0x42, 0x21, on_custom));
+    }
+
+    #[test]
+    fn nav_pvt_decodes_both_heights() {
+        let mut payload = nav_pvt_payload();
+        payload[32..36].copy_from_slice(&180_250i32.to_le_bytes()); // height above ellipsoid
+        payload[36..40].copy_from_slice(&205_000i32.to_le_bytes()); // hMSL
+        let fix = parse_fix(&nav_pvt_frame(&payload));
+
+        assert_eq!((fix.height_ellipsoid_mm, fix.height_msl), (180_250, 205_000));
+        assert_eq!(fix.altitude_ellipsoid_meters(), 180.25);
+        assert_eq!(fix.altitude_meters(), 205.0);
+        assert_eq!(fix.geoid_separation_meters(), -24.75);
+    }
+
+    #[test]
+    fn csv_writer_emits_header_and_fix_columns_in_order() {
+        let mut payload = nav_pvt_payload();
+        payload[36..40].copy_from_slice(&205_500i32.to_le_bytes());
+        payload[60..64].copy_from_slice(&1_250i32.to_le_bytes()); // gSpeed
+        let fix = parse_fix(&nav_pvt_frame(&payload));
+
+        let mut csv = CsvWriter::new(heapless::String::<160>::new());
+        csv.write_header().unwrap();
+        csv.write_fix(&fix).unwrap();
+        let out = csv.into_inner();
+
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let header: Vec<&str> = lines[0].split(',').collect();
+        let fields: Vec<&str> = lines[1].split(',').collect();
+        assert_eq!(header, ["timestamp", "lat", "lon", "alt", "speed", "sats", "fix_type"]);
+        assert_eq!(fields, ["2024-06-01T12:30:45Z", "29.7000000", "-97.5000000", "205.500", "1.250", "9", "3"]);
+    }
+
+    #[test]
+    fn configurator_replays_on_nmea_then_holds_off() {
+        let port = UbxConfig::get_port_config_ubx_only();
+        let msg = UbxConfig::build_cfg_msg(0x01, 0x07, 1);
+        let mut configurator = GpsConfigurator::new();
+        assert!(configurator.add_command(&port));
+        assert!(configurator.add_command(&msg));
+        assert!(!configurator.add_command(&[0u8; CONFIG_COMMAND_CAPACITY + 1]));
+
+        // Both commands in order, once
+        let mut serial = SerialMock::new(&[
+            SerialTransaction::write_many(port),
+            SerialTransaction::write_many(msg),
+            SerialTransaction::flush(),
+        ]);
+        assert_eq!(configurator.tick(false, &mut serial), Ok(false));
+        assert_eq!(configurator.tick(true, &mut serial), Ok(true));
+        assert_eq!(configurator.replay_count(), 1);
+
+        // NMEA still queued in the receiver doesn't trigger another replay
+        for _ in 0..CONFIG_REPLAY_HOLDOFF_BYTES {
+            assert_eq!(configurator.tick(true, &mut serial), Ok(false));
+        }
+        serial.done();
+        assert_eq!(configurator.replay_count(), 1);
+    }
+
+    #[test]
+    fn dead_reckon_moves_north_over_one_second() {
+        let mut fix = GpsDataBuilder::new().lat(30.0).lon(-97.5).alt(200.0).sats(9).valid().build();
+        fix.velocity_north = 10_000; // 10 m/s
+
+        let moved = fix.dead_reckon(1000);
+        assert!(moved.valid && moved.dead_reckoned);
+        // 10 m of latitude is 8.99e-5 degrees
+        assert_eq!(moved.latitude - fix.latitude, 899);
+        assert_eq!((moved.longitude, moved.height_msl), (fix.longitude, fix.height_msl));
+
+        // Past the limit the fix stays put and is marked invalid
+        let stale = fix.dead_reckon(DEAD_RECKON_MAX_MS + 1);
+        assert!(!stale.valid && stale.dead_reckoned);
+        assert_eq!(stale.latitude, fix.latitude);
+    }
+
+    #[test]
+    fn on_error_counts_a_corrupted_frame() {
+        use core::sync::atomic::{AtomicU32, Ordering};
+        use crate::sensors::test_vectors::{NAV_PVT, NAV_PVT_CORRUPTED};
+
+        static CHECKSUM_ERRORS: AtomicU32 = AtomicU32::new(0);
+        fn count(error: UbxError) {
+            if error == (UbxError::Checksum { class: 0x01, id: 0x07 }) {
+                CHECKSUM_ERRORS.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let mut parser: UbxParser = UbxParser::new();
+        parser.on_error(count);
+        assert!(parse_frame(&mut parser, &NAV_PVT_CORRUPTED).is_none());
+        assert_eq!(CHECKSUM_ERRORS.load(Ordering::Relaxed), 1);
+
+        // A good frame doesn't fire it
+        assert!(matches!(parse_frame(&mut parser, &NAV_PVT), Some(UbxEvent::NavPvt(_))));
+        assert_eq!(CHECKSUM_ERRORS.load(Ordering::Relaxed), 1);
+        assert_eq!(parser.stats().checksum_errors, 1);
+    }
+
+    #[test]
+    fn build_poll_matches_known_frames() {
+        assert_eq!(
+            UbxConfig::build_poll(UbxClass::Nav.into(), NavId::Pvt.into()),
+            [0xB5, 0x62, 0x01, 0x07, 0x00, 0x00, 0x08, 0x19]
+        );
+        let mon_ver = [0xB5, 0x62, 0x0A, 0x04, 0x00, 0x00, 0x0E, 0x34];
+        assert_eq!(UbxConfig::build_poll(0x0A, 0x04), mon_ver);
+        assert_eq!(UbxConfig::get_poll_mon_ver(), mon_ver);
+    }
+
+    #[test]
+    fn satellite_monitor_reports_crossings_and_ignores_invalid_fixes() {
+        let fix = |sats: u8| GpsDataBuilder::new().lat(29.7).lon(-97.5).sats(sats).valid().build();
+        let mut monitor = SatelliteMonitor::new(6);
+
+        assert_eq!(monitor.update(&fix(4)), None);
+        assert_eq!(monitor.update(&fix(6)), Some(SatEvent::Gained));
+        assert_eq!(monitor.update(&fix(9)), None);
+        // An invalid fix with too few satellites doesn't count as Lost
+        assert_eq!(monitor.update(&GpsDataBuilder::new().sats(0).build()), None);
+        assert_eq!(monitor.update(&fix(5)), Some(SatEvent::Lost));
+        assert_eq!(monitor.update(&fix(3)), None);
+        assert_eq!(monitor.update(&fix(7)), Some(SatEvent::Gained));
+    }
+
+    #[test]
+    fn verify_accepts_good_frames_and_rejects_corrupted_ones() {
+        let mon_ver = UbxConfig::get_poll_mon_ver();
+        assert!(UbxConfig::verify(&mon_ver));
+        assert!(UbxConfig::verify(&UbxConfig::get_enable_nav_pvt()));
+
+        let mut corrupted = UbxConfig::get_enable_nav_pvt();
+        corrupted[8] ^= 0x01; // Rate byte, checksum left as is
+        assert!(!UbxConfig::verify(&corrupted));
+        // Truncated and wrong-sync frames too
+        assert!(!UbxConfig::verify(&mon_ver[..7]));
+        assert!(!UbxConfig::verify(&[0x24, 0x62, 0x0A, 0x04, 0x00, 0x00, 0x0E, 0x34]));
+    }
+
+    #[test]
+    fn itow_decodes_and_gives_the_sub_second_phase() {
+        let phase = |itow_ms: u32, nano: i32| {
+            let mut payload = nav_pvt_payload();
+            payload[0..4].copy_from_slice(&itow_ms.to_le_bytes());
+            payload[16..20].copy_from_slice(&nano.to_le_bytes());
+            let fix = parse_fix(&nav_pvt_frame(&payload));
+            assert_eq!(fix.itow_ms, itow_ms);
+            fix.fraction_of_second()
+        };
+
+        assert_eq!(phase(123_456_000, 0), 0.0);
+        // nano refines iTOW's millisecond, rounded either way
+        assert!((phase(123_456_250, 250_000_200) - 0.250_000_2).abs() < 1e-6);
+        assert!((phase(123_456_251, 250_999_700) - 0.250_999_7).abs() < 1e-6);
+        // Just before the second: iTOW rounded up to the next one
+        assert!((phase(123_457_000, -300) - 0.999_999_7).abs() < 1e-6);
+        assert_eq!(parse_fix(&nav_pvt_frame(&nav_pvt_payload())).time_of_week_seconds(), 123_456.0);
+    }
+
+    #[test]
+    fn build_cfg_rst_frames() {
+        assert_eq!(
+            UbxConfig::build_cfg_rst(NAV_BBR_COLD_START, RESET_MODE_SW),
+            [0xB5, 0x62, 0x06, 0x04, 0x04, 0x00, 0xFF, 0xFF, 0x01, 0x00, 0x0D, 0x5F]
+        );
+        assert_eq!(
+            UbxConfig::build_cfg_rst(NAV_BBR_WARM_START, RESET_MODE_GNSS),
+            [0xB5, 0x62, 0x06, 0x04, 0x04, 0x00, 0x01, 0x00, 0x02, 0x00, 0x11, 0x6C]
+        );
+    }
+
+    #[test]
+    fn quality_tiers() {
+        let fix = |fix_type: u8, sats: u8, hacc_mm: u32| {
+            let mut fix = GpsDataBuilder::new().lat(29.7).lon(-97.5).sats(sats).valid().build();
+            fix.fix_type = fix_type;
+            fix.horizontal_accuracy = hacc_mm;
+            fix.quality()
+        };
+
+        assert_eq!(fix(0, 12, 1_000), FixQuality::NoFix);
+        assert_eq!(fix(5, 12, 1_000), FixQuality::NoFix); // Time only
+        assert_eq!(fix(3, 12, 1_500), FixQuality::Excellent);
+        assert_eq!(fix(4, 8, 1_999), FixQuality::Excellent); // GNSS + dead reckoning
+        assert_eq!(fix(3, 12, 2_000), FixQuality::Good);
+        assert_eq!(fix(3, 7, 1_000), FixQuality::Good);
+        assert_eq!(fix(3, 5, 4_000), FixQuality::Fair);
+        assert_eq!(fix(3, 9, 19_999), FixQuality::Fair);
+        assert_eq!(fix(3, 3, 1_000), FixQuality::Poor);
+        assert_eq!(fix(3, 12, 20_000), FixQuality::Poor);
+        // 2D never beats Poor
+        assert_eq!(fix(2, 12, 1_000), FixQuality::Poor);
+    }
+
+    #[test]
+    fn parse_all_collects_a_multi_message_capture() {
+        use crate::sensors::test_vectors::MIXED_STREAM;
+
+        let capture: Vec<u8> = MIXED_STREAM.concat();
+        let mut parser: UbxParser = UbxParser::new();
+        let mut events = Vec::new();
+        // Split mid-frame to check state carries over between calls
+        let (first, second) = capture.split_at(capture.len() / 2);
+        parser.parse_all(first, |event| events.push(event));
+        parser.parse_all(second, |event| events.push(event));
+
+        // NMEA skipped, corrupted fix dropped
+        assert_eq!(events.len(), 3);
+        assert!(matches!(events[0], UbxEvent::NavPvt(ref fix) if fix.satellites == 12));
+        assert!(matches!(events[1], UbxEvent::Ack { class: 0x06, id: 0x00 }));
+        assert!(matches!(events[2], UbxEvent::NavPvt(_)));
+    }
+
+    #[test]
+    fn fix_rate_meter_reports_10_hz() {
+        let mut meter = FixRateMeter::new(1000);
+        meter.record_fix(5_000);
+        assert_eq!(meter.hz(), 0.0);
+
+        // 100 ms spacing with a few ms of jitter
+        for (i, jitter) in [0, 3, -2, 1, -4, 2, 0, -1, 3, 1].into_iter().enumerate() {
+            let now = 5_000 + 100 * (i as i32 + 1) + jitter;
+            meter.record_fix(now as u32);
+            meter.tick(now as u32);
+        }
+        assert!((meter.hz() - 10.0).abs() < 0.3);
+        assert!((meter.instant_hz() - 10.0).abs() < 0.5);
+
+        // No fix within the timeout reports 0
+        meter.tick(6_001 + 1000);
+        assert!(meter.hz() > 0.0);
+        meter.tick(6_001 + 1001);
+        assert_eq!((meter.hz(), meter.instant_hz()), (0.0, 0.0));
+    }
+
+    #[cfg(feature = "diagnostics")]
+    #[test]
+    fn checksum_failure_is_recorded() {
+        use crate::sensors::test_vectors::{NAV_PVT, NAV_PVT_CORRUPTED};
+
+        let mut parser: UbxParser = UbxParser::new();
+        parser.parse_all(&NAV_PVT, |_| {});
+        assert_eq!(parser.recent_failures().count(), 0);
+
+        parser.parse_all(&NAV_PVT_CORRUPTED, |_| {});
+        let failures: Vec<_> = parser.recent_failures().copied().collect();
+        assert_eq!(failures, [ChecksumFailure {
+            class: 0x01,
+            id: 0x07,
+            length: 92,
+            payload: [0x00, 0x70, 0x99, 0x14, 0xE8, 0x07, 0x06, 0x01],
+            received: [0xFB, 0x8D],
+            // The flipped bit shows up in CK_A alone
+            calculated: [0xFF, 0x8D],
+        }]);
+
+        // Only the newest MAX_CHECKSUM_FAILURES are kept
+        for _ in 0..MAX_CHECKSUM_FAILURES + 2 {
+            parser.parse_all(&NAV_PVT_CORRUPTED, |_| {});
+        }
+        assert_eq!(parser.recent_failures().count(), MAX_CHECKSUM_FAILURES);
+    }
+
+    #[test]
+    fn dms_for_northeast_southwest_and_zero() {
+        let dms = |latitude: i32, longitude: i32| {
+            let mut fix = GpsData::new();
+            fix.latitude = latitude;
+            fix.longitude = longitude;
+            (fix.latitude_dms(), fix.longitude_dms())
+        };
+        let assert_dms = |(d, m, s, h): (i16, u8, f32, char), expected: (i16, u8, f32, char)| {
+            assert_eq!((d, m, h), (expected.0, expected.1, expected.3));
+            assert!((s - expected.2).abs() < 1e-4, "{s} vs {}", expected.2);
+        };
+
+        let (lat, lon) = dms(488_583_701, 22_944_813);
+        assert_dms(lat, (48, 51, 30.13236, 'N'));
+        assert_dms(lon, (2, 17, 40.13268, 'E'));
+
+        // Southern and western parts come out positive
+        let (lat, lon) = dms(-346_037_000, -583_816_000);
+        assert_dms(lat, (34, 36, 13.32, 'S'));
+        assert_dms(lon, (58, 22, 53.76, 'W'));
+
+        let (lat, lon) = dms(0, 0);
+        assert_dms(lat, (0, 0, 0.0, 'N'));
+        assert_dms(lon, (0, 0, 0.0, 'E'));
+    }
+
+    #[test]
+    fn enable_messages_emits_each_cfg_msg_in_order() {
+        // Records every byte written
+        struct Capture(Vec<u8>);
+        impl embedded_hal_nb::serial::ErrorType for Capture {
+            type Error = core::convert::Infallible;
+        }
+        impl embedded_hal_nb::serial::Write<u8> for Capture {
+            fn write(&mut self, byte: u8) -> nb::Result<(), Self::Error> {
+                self.0.push(byte);
+                Ok(())
+            }
+            fn flush(&mut self) -> nb::Result<(), Self::Error> {
+                Ok(())
+            }
+        }
+
+        let msgs = [(0x01, 0x07, 1), (0x01, 0x35, 5), (0x01, 0x04, 1)]; // NAV-PVT, NAV-SAT, NAV-DOP
+        let mut tx = Capture(Vec::new());
+        assert_eq!(UbxConfig::enable_messages(&msgs, &mut tx), Ok(3));
+
+        let frames: Vec<&[u8]> = tx.0.chunks(11).collect();
+        assert_eq!(frames.len(), 3);
+        for (frame, &(class, id, rate)) in frames.iter().zip(&msgs) {
+            assert!(UbxConfig::verify(frame));
+            assert_eq!(&frame[2..9], &[0x06, 0x01, 0x03, 0x00, class, id, rate]);
+        }
+    }
+
+    #[test]
+    fn kml_coord_is_lon_lat_alt() {
+        let fix = GpsDataBuilder::new().lat(29.7123456).lon(-97.5000001).alt(205.25).sats(9).valid().build();
+        let mut buf = [0u8; 48];
+        let len = fix.to_kml_coord(&mut buf).unwrap();
+        assert_eq!(core::str::from_utf8(&buf[..len]), Ok("-97.5000001,29.7123456,205.250"));
+
+        // Too small a buffer, or no fix, is an error rather than a truncated tuple
+        assert_eq!(fix.to_kml_coord(&mut buf[..len - 1]), Err(KmlError::BufferTooSmall));
+        assert_eq!(GpsData::new().to_kml_coord(&mut buf), Err(KmlError::InvalidFix));
+    }
+
+    #[test]
+    fn altitude_filter_tracks_a_noisy_climb() {
+        // 2 m/s climb from 100 m at 10 Hz with up to ±1.5 m of noise
+        let mut noise_state = 12345u32;
+        let mut noise_mm = || {
+            noise_state = noise_state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (noise_state >> 16) as i32 % 3001 - 1500
+        };
+        let mut filter = AltitudeFilter::new();
+
+        let mut last = None;
+        for i in 0..600u32 {
+            let true_mm = 100_000 + 200 * i as i32;
+            let mut fix = GpsDataBuilder::new().sats(9).valid().build();
+            fix.height_msl = true_mm + noise_mm();
+            last = filter.update(&fix, 1_000 + 100 * i);
+        }
+
+        let (altitude, rate) = last.unwrap();
+        assert!((altitude - 219.8).abs() < 1.0, "altitude {altitude}");
+        assert!((rate - 2.0).abs() < 0.3, "rate {rate}");
+
+        // Invalid fixes and repeated timestamps are rejected without touching the state
+        assert!(filter.update(&GpsData::new(), 61_000).is_none());
+        let fix = GpsDataBuilder::new().alt(220.0).valid().build();
+        assert!(filter.update(&fix, 1_000 + 100 * 599).is_none());
+        assert_eq!((filter.altitude_meters(), filter.vertical_rate_ms()), (altitude, rate));
+    }
+
+    #[test]
+    fn build_nmea_msg_frames_gga_and_rmc() {
+        assert_eq!(
+            UbxConfig::build_nmea_msg(NMEA_GGA, 1),
+            [0xB5, 0x62, 0x06, 0x01, 0x03, 0x00, 0xF0, 0x00, 0x01, 0xFB, 0x10]
+        );
+        assert_eq!(
+            UbxConfig::build_nmea_msg(NMEA_RMC, 1),
+            [0xB5, 0x62, 0x06, 0x01, 0x03, 0x00, 0xF0, 0x04, 0x01, 0xFF, 0x18]
+        );
+        // Rate 0 turns a sentence off
+        assert!(UbxConfig::verify(&UbxConfig::build_nmea_msg(NMEA_GSV, 0)));
+    }
+
+    #[test]
+    fn builder_sets_the_fields_and_default_is_empty() {
+        let fix = GpsDataBuilder::new().lat(29.7123456).lon(-97.5).alt(205.25).sats(12).valid().build();
+        assert_eq!((fix.latitude, fix.longitude, fix.height_msl), (297_123_456, -975_000_000, 205_250));
+        assert_eq!(fix.satellites, 12);
+        assert!(fix.valid);
+        assert_eq!(fix.fix_type, 3);
+
+        let empty = GpsData::default();
+        assert!(!empty.valid);
+        assert_eq!((empty.latitude, empty.longitude, empty.satellites, empty.fix_type), (0, 0, 0, 0));
+        // Unset builder fields keep the defaults
+        let partial = GpsDataBuilder::new().sats(5).build();
+        assert!(!partial.valid);
+        assert_eq!((partial.latitude, partial.height_msl, partial.satellites), (0, 0, 5));
+    }
+
+    #[test]
+    fn accuracy_circle_converts_hacc_to_meters() {
+        let fix = parse_fix(&nav_pvt_frame(&nav_pvt_payload())); // hAcc 1500 mm
+        assert_eq!(fix.accuracy_circle_meters(), Some(1.5));
+
+        let mut lost = fix;
+        lost.valid = false;
+        assert_eq!(lost.accuracy_circle_meters(), None);
+    }
+
+    #[test]
+    fn cfg_prt_poll_and_response_decode() {
+        assert_eq!(UbxConfig::build_cfg_prt_poll(1), [0xB5, 0x62, 0x06, 0x00, 0x01, 0x00, 0x01, 0x08, 0x22]);
+
+        // UART1 at 38400 8N1, UBX+NMEA+RTCM in, UBX+NMEA out
+        let response = [
+            0xB5, 0x62, 0x06, 0x00, 0x14, 0x00, 0x01, 0x00, 0x00, 0x00, 0xC0, 0x08, 0x00, 0x00, 0x00, 0x96,
+            0x00, 0x00, 0x07, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x83, 0x90,
+        ];
+        let mut parser: UbxParser = UbxParser::new();
+        let Some(UbxEvent::CfgPrt(config)) = parse_frame(&mut parser, &response) else {
+            panic!("expected a CfgPrt event");
+        };
+        assert_eq!(config, PortConfig { port: 1, baud: 38_400, in_proto: 0x0007, out_proto: PROTO_UBX | PROTO_NMEA });
+    }
+
+    #[test]
+    fn feed_budgeted_resumes_a_frame_split_across_calls() {
+        use crate::sensors::test_vectors::{ACK_ACK_CFG_PRT, NAV_PVT};
+
+        let stream = [NAV_PVT.as_slice(), &ACK_ACK_CFG_PRT].concat();
+        let mut parser: UbxParser = UbxParser::new();
+
+        // The first budget ends mid-frame
+        assert!(matches!(parser.feed_budgeted(&stream, 64), (64, None)));
+        // The rest of the fix completes in the next call, which stops right after it
+        let (consumed, event) = parser.feed_budgeted(&stream[64..], 64);
+        assert_eq!(consumed, NAV_PVT.len() - 64);
+        assert!(matches!(event, Some(UbxEvent::NavPvt(_))));
+
+        let rest = &stream[NAV_PVT.len()..];
+        let (consumed, event) = parser.feed_budgeted(rest, 64);
+        assert_eq!(consumed, ACK_ACK_CFG_PRT.len());
+        assert!(matches!(event, Some(UbxEvent::Ack { class: 0x06, id: 0x00 })));
+        assert!(matches!(parser.feed_budgeted(&[], 64), (0, None)));
+    }
+
+    #[test]
+    fn speed_in_knots_and_kmh() {
+        let mut fix = GpsDataBuilder::new().valid().build();
+        fix.ground_speed = 10_000; // 10 m/s
+        assert!((fix.speed_knots() - 19.43844).abs() < 1e-5);
+        assert!((fix.speed_kmh() - 36.0).abs() < 1e-9);
+
+        // Stationary still reports a number
+        fix.ground_speed = 0;
+        assert_eq!((fix.speed_knots(), fix.speed_kmh()), (0.0, 0.0));
+    }
+
+    #[test]
+    fn probe_classifies_the_stream() {
+        use crate::sensors::test_vectors::{NAV_PVT, NMEA_NOISE};
+
+        // `bytes`, then silence for the rest of a 1 ms window
+        let probe = |bytes: &[u8]| {
+            let mut transactions = vec![SerialTransaction::read_many(bytes)];
+            transactions.extend((0..1000 / ACK_POLL_US).map(|_| SerialTransaction::read_error(nb::Error::WouldBlock)));
+            let mut serial = SerialMock::new(&transactions);
+            let result = GpsDiagnostics::probe(&mut serial, &mut NoopDelay, 1);
+            serial.done();
+            result
+        };
+
+        assert_eq!(probe(NMEA_NOISE), ProbeResult::NmeaDetected);
+        assert_eq!(probe(&[]), ProbeResult::Nothing);
+        assert_eq!(probe(&[0x7F, 0xF8, 0x80, 0xE0, 0x00, 0xFE]), ProbeResult::Garbage);
+
+        // UBX returns on the sync pair without waiting out the window
+        let mut serial = SerialMock::new(&[SerialTransaction::read_many(&NAV_PVT[..2])]);
+        assert_eq!(GpsDiagnostics::probe(&mut serial, &mut NoopDelay, 1000), ProbeResult::UbxDetected);
+        serial.done();
     }
 }
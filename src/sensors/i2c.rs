@@ -0,0 +1,154 @@
+use embedded_hal::i2c::I2c;
+
+use super::RegisterBus;
+use crate::Error;
+
+// ST sensors only auto-increment the register address on multi-byte
+// transfers when the MSB of the sub-address is set
+pub const ST_AUTO_INCREMENT: u8 = 0x80;
+
+/// Register-level access to an I2C device at a fixed address
+pub struct I2cRegisters<I2C> {
+    i2c: I2C,
+    address: u8,
+    auto_increment: u8, // OR'd into the start register for multi-byte reads
+}
+
+impl<I2C: I2c> I2cRegisters<I2C> {
+    /// `auto_increment` is the sub-address flag the device needs for burst
+    /// reads (`ST_AUTO_INCREMENT`), or 0 if it increments unconditionally
+    pub fn new(i2c: I2C, address: u8, auto_increment: u8) -> Self {
+        Self {
+            i2c,
+            address,
+            auto_increment,
+        }
+    }
+
+    /// Get the 7-bit device address
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// Give back the underlying bus
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+
+    /// Write a single register
+    pub fn write_reg(&mut self, register: u8, value: u8) -> Result<(), I2C::Error> {
+        self.i2c.write(self.address, &[register, value])
+    }
+
+    /// Read a single register
+    pub fn read_reg(&mut self, register: u8) -> Result<u8, I2C::Error> {
+        let mut buffer = [0u8; 1];
+        self.i2c.write_read(self.address, &[register], &mut buffer)?;
+        Ok(buffer[0])
+    }
+
+    /// Burst-read consecutive registers starting at `start_register`
+    pub fn read_regs(&mut self, start_register: u8, buffer: &mut [u8]) -> Result<(), I2C::Error> {
+        let register = if buffer.len() > 1 {
+            start_register | self.auto_increment
+        } else {
+            start_register
+        };
+        self.i2c.write_read(self.address, &[register], buffer)
+    }
+}
+
+impl<I2C: I2c> RegisterBus for I2cRegisters<I2C> {
+    type Error = I2C::Error;
+
+    fn write_reg(&mut self, register: u8, value: u8) -> Result<(), Error<I2C::Error>> {
+        I2cRegisters::write_reg(self, register, value).map_err(Error::I2c)
+    }
+
+    fn read_reg(&mut self, register: u8) -> Result<u8, Error<I2C::Error>> {
+        I2cRegisters::read_reg(self, register).map_err(Error::I2c)
+    }
+
+    fn read_regs(&mut self, start_register: u8, buffer: &mut [u8]) -> Result<(), Error<I2C::Error>> {
+        I2cRegisters::read_regs(self, start_register, buffer).map_err(Error::I2c)
+    }
+}
+
+// Clock pulses that always free a slave stuck mid-byte: 8 data bits + ACK
+pub const I2C_RECOVERY_PULSES: u8 = 9;
+
+/// Pulse SCL until SDA reads high, at most I2C_RECOVERY_PULSES times
+/// Returns the number of pulses needed, or None if SDA is still held low
+pub fn clock_out_stuck_slave(
+    mut sda_is_high: impl FnMut() -> bool,
+    mut pulse_scl: impl FnMut(),
+) -> Option<u8> {
+    let mut pulses = 0;
+    while !sda_is_high() {
+        if pulses == I2C_RECOVERY_PULSES {
+            return None;
+        }
+        pulse_scl();
+        pulses += 1;
+    }
+    Some(pulses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_hal::i2c::ErrorKind;
+    use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction};
+
+    #[test]
+    fn register_access_sets_auto_increment_only_for_bursts() {
+        let mut i2c = I2cMock::new(&[
+            Transaction::write(0x1C, vec![0x20, 0x70]),
+            Transaction::write_read(0x1C, vec![0x0F], vec![0x3D]),
+            // A one-byte "burst" is a plain register read
+            Transaction::write_read(0x1C, vec![0x28], vec![0x12]),
+            Transaction::write_read(0x1C, vec![0x28 | ST_AUTO_INCREMENT], vec![0x12, 0x34]),
+        ]);
+        let mut regs = I2cRegisters::new(i2c.clone(), 0x1C, ST_AUTO_INCREMENT);
+
+        regs.write_reg(0x20, 0x70).unwrap();
+        assert_eq!(regs.read_reg(0x0F).unwrap(), 0x3D);
+        let mut one = [0u8; 1];
+        regs.read_regs(0x28, &mut one).unwrap();
+        let mut two = [0u8; 2];
+        regs.read_regs(0x28, &mut two).unwrap();
+        assert_eq!((one, two), ([0x12], [0x12, 0x34]));
+        i2c.done();
+    }
+
+    #[test]
+    fn register_bus_wraps_errors_and_plain_devices_skip_the_flag() {
+        let mut i2c = I2cMock::new(&[
+            Transaction::write_read(0x68, vec![0x3B], vec![0; 6]),
+            Transaction::write(0x68, vec![0x6B, 0x00]).with_error(ErrorKind::Other),
+        ]);
+        // A device that increments on its own: no flag OR'd in
+        let mut regs = I2cRegisters::new(i2c.clone(), 0x68, 0);
+
+        let mut buffer = [0u8; 6];
+        RegisterBus::read_regs(&mut regs, 0x3B, &mut buffer).unwrap();
+        assert_eq!(RegisterBus::write_reg(&mut regs, 0x6B, 0x00), Err(Error::I2c(ErrorKind::Other)));
+        i2c.done();
+    }
+
+    #[test]
+    fn stuck_slave_is_clocked_out_within_nine_pulses() {
+        // SDA released after `hold` pulses, counting how many were sent
+        let run = |hold: u8| {
+            let pulses = core::cell::Cell::new(0u8);
+            let result = clock_out_stuck_slave(|| pulses.get() >= hold, || pulses.set(pulses.get() + 1));
+            (result, pulses.get())
+        };
+
+        assert_eq!(run(0), (Some(0), 0)); // Bus already free
+        assert_eq!(run(3), (Some(3), 3));
+        assert_eq!(run(I2C_RECOVERY_PULSES), (Some(I2C_RECOVERY_PULSES), I2C_RECOVERY_PULSES));
+        // Still low after the ninth pulse: give up without a tenth
+        assert_eq!(run(u8::MAX), (None, I2C_RECOVERY_PULSES));
+    }
+}
@@ -1,8 +1,19 @@
+use core::cell::RefCell;
+
+use embedded_hal::delay::DelayNs;
 use embedded_hal::i2c::I2c;
-use rtt_target::rprintln;
+use embedded_hal::spi::SpiDevice;
+use embedded_hal_bus::i2c::RefCellDevice;
+
+use super::i2c::{I2cRegisters, ST_AUTO_INCREMENT};
+use super::spi::SpiRegisters;
+use super::{Magnetometer, RegisterBus};
+pub use crate::error::Error;
 
 // LIS3MDL I2C address (when SA1 pin is connected to GND)
 pub const LIS3MDL_ADDRESS: u8 = 0x1C;
+// LIS3MDL I2C address when SA1 is connected to VDD
+pub const LIS3MDL_ADDRESS_ALT: u8 = 0x1E;
 
 // LIS3MDL Register addresses
 pub const WHO_AM_I: u8 = 0x0F;
@@ -20,10 +31,71 @@ pub const OUT_Z_L: u8 = 0x2C;
 pub const OUT_Z_H: u8 = 0x2D;
 pub const TEMP_OUT_L: u8 = 0x2E;
 pub const TEMP_OUT_H: u8 = 0x2F;
+pub const INT_CFG: u8 = 0x30;
+pub const INT_SRC: u8 = 0x31;
+pub const INT_THS_L: u8 = 0x32;
+pub const INT_THS_H: u8 = 0x33;
+
+// INT_CFG axis enable bits (combine with | for configure_interrupt)
+pub const INT_X_AXIS: u8 = 0x80;
+pub const INT_Y_AXIS: u8 = 0x40;
+pub const INT_Z_AXIS: u8 = 0x20;
+
+// INT_CFG control bits
+const INT_CFG_RESERVED: u8 = 0x08; // Bit 3 must always be written as 1
+const INT_CFG_IEA: u8 = 0x04;      // Interrupt active high
+const INT_CFG_LIR: u8 = 0x02;      // Set = NOT latched (inverted sense)
+const INT_CFG_IEN: u8 = 0x01;      // Interrupt enable
+
+// Interrupt generator settings, as passed to configure_interrupt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterruptConfig {
+    pub axes: u8,          // INT_X_AXIS | INT_Y_AXIS | INT_Z_AXIS
+    pub threshold: u16,    // Unsigned threshold in LSB (15 bits)
+    pub active_high: bool, // INT pin polarity
+    pub latch: bool,       // INT stays asserted until INT_SRC is read
+}
 
 // Expected WHO_AM_I value for LIS3MDL
 pub const LIS3MDL_WHO_AM_I_VALUE: u8 = 0x3D;
 
+// First delay between WHO_AM_I attempts in init_with_retries, doubled per retry
+pub const WHO_AM_I_RETRY_DELAY_MS: u32 = 2;
+
+// Temperature sensor defaults from the datasheet: 8 LSB/°C, 0 LSB = 25°C
+pub const TEMP_DEFAULT_OFFSET_C: f32 = 25.0;
+pub const TEMP_DEFAULT_LSB_PER_C: f32 = 8.0;
+
+// Temperature at which the sensitivity temperature coefficient is zero
+pub const TEMP_COEFF_REFERENCE_C: f32 = 25.0;
+
+// STATUS_REG new X/Y/Z data available bit
+const STATUS_ZYXDA: u8 = 0x08;
+
+// CTRL_REG1 temperature sensor enable bit
+const CTRL_REG1_TEMP_EN: u8 = 0x80;
+
+// CTRL_REG1 FAST_ODR bit, output rates above 80 Hz
+const CTRL_REG1_FAST_ODR: u8 = 0x02;
+
+// CTRL_REG2 full-scale field FS[1:0]
+const CTRL_REG2_FS_MASK: u8 = 0x60;
+
+// CTRL_REG1 bits kept by set_data_rate: TEMP_EN and ST
+const CTRL_REG1_KEEP_MASK: u8 = 0x81;
+
+// CTRL_REG4 Z-axis performance mode field OMZ[1:0]
+const CTRL_REG4_OMZ_MASK: u8 = 0x0C;
+
+// Sample period in ms for each CTRL_REG1 DO[2:0] setting (0.625 Hz .. 80 Hz)
+const ODR_PERIOD_MS: [u32; 8] = [1600, 800, 400, 200, 100, 50, 25, 13];
+
+// Sample period in ms with FAST_ODR set, per CTRL_REG1 OM[1:0] (1000/560/300/155 Hz)
+const FAST_ODR_PERIOD_MS: [u32; 4] = [1, 2, 4, 7];
+
+// Raw readings this close to i16::MIN/MAX are treated as clipped
+pub const SATURATION_MARGIN: i16 = 8;
+
 // Performance modes
 #[derive(Debug, Clone, Copy)]
 pub enum PerformanceMode {
@@ -44,6 +116,10 @@ pub enum DataRate {
     Hz20,     // 20 Hz
     Hz40,     // 40 Hz
     Hz80,     // 80 Hz
+    Hz155,    // 155 Hz, FAST_ODR in ultra-high performance mode
+    Hz300,    // 300 Hz, FAST_ODR in high performance mode
+    Hz560,    // 560 Hz, FAST_ODR in medium performance mode
+    Hz1000,   // 1000 Hz, FAST_ODR in low-power mode
 }
 
 // Full scale selection
@@ -55,7 +131,215 @@ pub enum FullScale {
     Gauss16,  // ±16 gauss
 }
 
-#[derive(Debug)]
+impl FullScale {
+    /// FS[1:0] field, already shifted into place for CTRL_REG2
+    fn ctrl_reg2_bits(self) -> u8 {
+        let fs = match self {
+            FullScale::Gauss4 => 0b00,
+            FullScale::Gauss8 => 0b01,
+            FullScale::Gauss12 => 0b10,
+            FullScale::Gauss16 => 0b11,
+        };
+        fs << 5
+    }
+
+    fn from_ctrl_reg2(reg: u8) -> Self {
+        match (reg >> 5) & 0b11 {
+            0b00 => FullScale::Gauss4,
+            0b01 => FullScale::Gauss8,
+            0b10 => FullScale::Gauss12,
+            _ => FullScale::Gauss16,
+        }
+    }
+}
+
+impl PerformanceMode {
+    /// 2-bit OM/OMZ field value, unshifted
+    fn bits(self) -> u8 {
+        match self {
+            PerformanceMode::LowPower => 0b00,
+            PerformanceMode::Medium => 0b01,
+            PerformanceMode::High => 0b10,
+            PerformanceMode::UltraHigh => 0b11,
+        }
+    }
+
+    /// Decode a 2-bit OM (CTRL_REG1) or OMZ (CTRL_REG4) field
+    fn from_bits(bits: u8) -> Self {
+        match bits & 0b11 {
+            0b00 => PerformanceMode::LowPower,
+            0b01 => PerformanceMode::Medium,
+            0b10 => PerformanceMode::High,
+            _ => PerformanceMode::UltraHigh,
+        }
+    }
+}
+
+impl DataRate {
+    /// Performance mode the sensor actually runs at this rate
+    ///
+    /// With FAST_ODR set the DO bits are ignored and the rate follows from the
+    /// performance mode instead (datasheet table 22): 1000 Hz in low-power,
+    /// 560 Hz in medium, 300 Hz in high and 155 Hz in ultra-high performance.
+    /// The fast rates therefore override `requested`; the others keep it.
+    pub fn performance_mode(self, requested: PerformanceMode) -> PerformanceMode {
+        match self {
+            DataRate::Hz155 => PerformanceMode::UltraHigh,
+            DataRate::Hz300 => PerformanceMode::High,
+            DataRate::Hz560 => PerformanceMode::Medium,
+            DataRate::Hz1000 => PerformanceMode::LowPower,
+            _ => requested,
+        }
+    }
+
+    /// CTRL_REG1 OM, DO and FAST_ODR bits for this rate (TEMP_EN and ST left clear)
+    pub fn ctrl_reg1_bits(self, requested: PerformanceMode) -> u8 {
+        let om = self.performance_mode(requested).bits() << 5;
+        let odr = match self {
+            DataRate::Hz0_625 => 0,
+            DataRate::Hz1_25 => 1,
+            DataRate::Hz2_5 => 2,
+            DataRate::Hz5 => 3,
+            DataRate::Hz10 => 4,
+            DataRate::Hz20 => 5,
+            DataRate::Hz40 => 6,
+            DataRate::Hz80 => 7,
+            DataRate::Hz155 | DataRate::Hz300 | DataRate::Hz560 | DataRate::Hz1000 => {
+                return om | CTRL_REG1_FAST_ODR;
+            }
+        };
+        om | (odr << 2)
+    }
+
+    /// Rate selected by FAST_ODR in the given performance mode
+    fn fast(mode: PerformanceMode) -> Self {
+        match mode {
+            PerformanceMode::LowPower => DataRate::Hz1000,
+            PerformanceMode::Medium => DataRate::Hz560,
+            PerformanceMode::High => DataRate::Hz300,
+            PerformanceMode::UltraHigh => DataRate::Hz155,
+        }
+    }
+
+    /// Decode the DO[2:0] field of CTRL_REG1
+    fn from_bits(bits: u8) -> Self {
+        match bits & 0b111 {
+            0 => DataRate::Hz0_625,
+            1 => DataRate::Hz1_25,
+            2 => DataRate::Hz2_5,
+            3 => DataRate::Hz5,
+            4 => DataRate::Hz10,
+            5 => DataRate::Hz20,
+            6 => DataRate::Hz40,
+            _ => DataRate::Hz80,
+        }
+    }
+}
+
+// Conversion mode, MD[1:0] in CTRL_REG3
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperatingMode {
+    Continuous, // 00
+    Single,     // 01: one conversion, then power-down
+    PowerDown,  // 10 or 11
+}
+
+// CTRL_REG1..CTRL_REG5 as read by dump_control_registers, with the
+// configuration fields broken out
+#[derive(Debug, Clone, Copy)]
+pub struct ControlRegs {
+    pub raw: [u8; 5],                  // CTRL_REG1..CTRL_REG5 as read
+    pub temp_enabled: bool,            // CTRL_REG1 TEMP_EN
+    pub xy_mode: PerformanceMode,      // CTRL_REG1 OM
+    pub data_rate: DataRate,           // CTRL_REG1 DO, or OM when FAST_ODR is set
+    pub fast_odr: bool,                // CTRL_REG1 FAST_ODR
+    pub self_test: bool,               // CTRL_REG1 ST
+    pub full_scale: FullScale,         // CTRL_REG2 FS
+    pub operating_mode: OperatingMode, // CTRL_REG3 MD
+    pub z_mode: PerformanceMode,       // CTRL_REG4 OMZ
+    pub big_endian: bool,              // CTRL_REG4 BLE
+    pub fast_read: bool,               // CTRL_REG5 FAST_READ
+    pub block_data_update: bool,       // CTRL_REG5 BDU
+}
+
+impl ControlRegs {
+    pub fn from_bytes(raw: [u8; 5]) -> Self {
+        let [reg1, reg2, reg3, reg4, reg5] = raw;
+        let xy_mode = PerformanceMode::from_bits(reg1 >> 5);
+        let fast_odr = reg1 & CTRL_REG1_FAST_ODR != 0;
+        Self {
+            raw,
+            temp_enabled: reg1 & CTRL_REG1_TEMP_EN != 0,
+            xy_mode,
+            data_rate: if fast_odr { DataRate::fast(xy_mode) } else { DataRate::from_bits(reg1 >> 2) },
+            fast_odr,
+            self_test: reg1 & 0x01 != 0,
+            full_scale: FullScale::from_ctrl_reg2(reg2),
+            operating_mode: match reg3 & 0b11 {
+                0b00 => OperatingMode::Continuous,
+                0b01 => OperatingMode::Single,
+                _ => OperatingMode::PowerDown,
+            },
+            z_mode: PerformanceMode::from_bits(reg4 >> 2),
+            big_endian: reg4 & 0x02 != 0,
+            fast_read: reg5 & 0x80 != 0,
+            block_data_update: reg5 & 0x40 != 0,
+        }
+    }
+}
+
+// Sensor axes, used as remap sources
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+// Source axis and negate flag for each output axis (X, Y, Z)
+pub type AxisMap = [(Axis, bool); 3];
+
+// Sensor frame equals the output frame
+pub const AXIS_MAP_IDENTITY: AxisMap = [(Axis::X, false), (Axis::Y, false), (Axis::Z, false)];
+
+// No rotation, as [w, x, y, z]
+pub const QUATERNION_IDENTITY: [f32; 4] = [1.0, 0.0, 0.0, 0.0];
+
+/// Rotate a vector by a unit quaternion [w, x, y, z] (q v q*)
+pub fn rotate_by_quaternion(q: [f32; 4], v: [f32; 3]) -> [f32; 3] {
+    let [w, qx, qy, qz] = q;
+    let cross = |a: [f32; 3], b: [f32; 3]| {
+        [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+    };
+
+    // v' = v + 2w (u x v) + 2 u x (u x v), with u the vector part
+    let u = [qx, qy, qz];
+    let t = cross(u, v).map(|c| 2.0 * c);
+    let ut = cross(u, t);
+    [
+        v[0] + w * t[0] + ut[0],
+        v[1] + w * t[1] + ut[1],
+        v[2] + w * t[2] + ut[2],
+    ]
+}
+
+/// Remap raw sensor axes into the output frame
+/// Negation saturates so i16::MIN maps to i16::MAX instead of overflowing
+pub fn apply_axis_map(map: &AxisMap, raw: [i16; 3]) -> [i16; 3] {
+    let mut out = [0i16; 3];
+    for (value, (source, negate)) in out.iter_mut().zip(map) {
+        let v = match source {
+            Axis::X => raw[0],
+            Axis::Y => raw[1],
+            Axis::Z => raw[2],
+        };
+        *value = if *negate { v.saturating_neg() } else { v };
+    }
+    out
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct MagnetometerData {
     pub x: i16,
     pub y: i16,
@@ -63,35 +347,422 @@ pub struct MagnetometerData {
     pub temperature: i16,
 }
 
-pub struct Lis3mdl<I2C> {
-    i2c: I2C,
-    address: u8,
+impl MagnetometerData {
+    /// Check if any axis is within SATURATION_MARGIN of the i16 rails
+    pub fn is_saturated(&self) -> bool {
+        let near_rail = |v: i16| v >= i16::MAX - SATURATION_MARGIN || v <= i16::MIN + SATURATION_MARGIN;
+        near_rail(self.x) || near_rail(self.y) || near_rail(self.z)
+    }
+}
+
+// Rolling-average filter over the last N magnetometer samples
+pub struct MagFilter<const N: usize> {
+    samples: [[i16; 3]; N],
+    index: usize,
+    count: usize,
+    sum: [i32; 3], // Running per-axis sums (i32 so N * i16::MAX can't overflow)
+}
+
+impl<const N: usize> MagFilter<N> {
+    pub fn new() -> Self {
+        Self {
+            samples: [[0; 3]; N],
+            index: 0,
+            count: 0,
+            sum: [0; 3],
+        }
+    }
+
+    /// Add a sample, evicting the oldest once the window is full
+    pub fn push(&mut self, sample: MagnetometerData) {
+        if N == 0 {
+            return;
+        }
+
+        let new = [sample.x, sample.y, sample.z];
+        if self.count == N {
+            let old = self.samples[self.index];
+            for (sum, value) in self.sum.iter_mut().zip(old) {
+                *sum -= value as i32;
+            }
+        } else {
+            self.count += 1;
+        }
+
+        for (sum, value) in self.sum.iter_mut().zip(new) {
+            *sum += value as i32;
+        }
+        self.samples[self.index] = new;
+        self.index = (self.index + 1) % N;
+    }
+
+    /// Componentwise mean of the buffered samples in raw LSB
+    /// Returns zeros until the first sample has been pushed
+    pub fn average(&self) -> (f32, f32, f32) {
+        if self.count == 0 {
+            return (0.0, 0.0, 0.0);
+        }
+
+        let n = self.count as f32;
+        (
+            self.sum[0] as f32 / n,
+            self.sum[1] as f32 / n,
+            self.sum[2] as f32 / n,
+        )
+    }
+
+    /// Number of samples currently in the window
+    pub fn sample_count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<const N: usize> Default for MagFilter<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Block-average downsampler: every FACTOR raw samples become one averaged
+// sample, so a 300 Hz stream can be logged at e.g. 30 Hz with less noise
+pub struct Decimator<const FACTOR: usize> {
+    sum: [i32; 4], // x, y, z, temperature
+    count: usize,
+}
+
+impl<const FACTOR: usize> Decimator<FACTOR> {
+    pub fn new() -> Self {
+        Self {
+            sum: [0; 4],
+            count: 0,
+        }
+    }
+
+    /// Add a sample; returns the mean of the block it completes, if any
+    /// Means are truncated toward zero. A FACTOR of 0 behaves like 1
+    pub fn push(&mut self, sample: MagnetometerData) -> Option<MagnetometerData> {
+        let values = [sample.x, sample.y, sample.z, sample.temperature];
+        for (sum, value) in self.sum.iter_mut().zip(values) {
+            *sum += value as i32;
+        }
+        self.count += 1;
+        if self.count < FACTOR {
+            return None;
+        }
+
+        // The mean of i16 values always fits back into an i16
+        let n = self.count as i32;
+        let [x, y, z, temperature] = self.sum.map(|sum| (sum / n) as i16);
+        self.sum = [0; 4];
+        self.count = 0;
+        Some(MagnetometerData { x, y, z, temperature })
+    }
+
+    /// Drop a partially filled block
+    pub fn reset(&mut self) {
+        self.sum = [0; 4];
+        self.count = 0;
+    }
+}
+
+impl<const FACTOR: usize> Default for Decimator<FACTOR> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Hard/soft-iron calibration from rotating the sensor through all orientations
+//
+// Tracks the per-axis min/max of raw samples. The center of each range is the
+// hard-iron offset; the ratio of the mean half-range to each axis' half-range
+// is a diagonal soft-iron scale. Both are in the frame of the samples pushed,
+// i.e. after any axis remap, which is what Lis3mdl::set_calibration expects.
+pub struct MagCalibrator {
+    min: [i16; 3],
+    max: [i16; 3],
+    count: u32,
+}
+
+impl MagCalibrator {
+    pub fn new() -> Self {
+        Self {
+            min: [i16::MAX; 3],
+            max: [i16::MIN; 3],
+            count: 0,
+        }
+    }
+
+    pub fn push(&mut self, sample: &MagnetometerData) {
+        for (axis, value) in [sample.x, sample.y, sample.z].into_iter().enumerate() {
+            self.min[axis] = self.min[axis].min(value);
+            self.max[axis] = self.max[axis].max(value);
+        }
+        self.count += 1;
+    }
+
+    /// Number of samples pushed
+    pub fn sample_count(&self) -> u32 {
+        self.count
+    }
+
+    /// Per-axis (offsets, scales): offsets in raw LSB, scales as multipliers
+    /// Returns zero offsets and unit scales until every axis has a nonzero range
+    pub fn finish(&self) -> ([f32; 3], [f32; 3]) {
+        let half_range = self.half_ranges();
+        if self.count == 0 || half_range.iter().any(|&r| r <= 0.0) {
+            return ([0.0; 3], [1.0; 3]);
+        }
+
+        let mean_range = (half_range[0] + half_range[1] + half_range[2]) / 3.0;
+        let mut offsets = [0.0; 3];
+        let mut scales = [1.0; 3];
+        for axis in 0..3 {
+            offsets[axis] = (self.max[axis] as f32 + self.min[axis] as f32) / 2.0;
+            scales[axis] = mean_range / half_range[axis];
+        }
+        (offsets, scales)
+    }
+
+    /// Rotation coverage heuristic in 0.0..=1.0
+    ///
+    /// The Earth field has the same magnitude in every direction, so a full
+    /// rotation gives roughly equal ranges on all three axes. Coverage is
+    /// the smallest axis range over the largest; spinning only about one
+    /// axis leaves that axis' range small and the value low. Soft-iron
+    /// distortion keeps a complete calibration somewhat below 1.0.
+    pub fn coverage(&self) -> f32 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        let half_range = self.half_ranges();
+        let largest = half_range[0].max(half_range[1]).max(half_range[2]);
+        if largest <= 0.0 {
+            return 0.0;
+        }
+        half_range[0].min(half_range[1]).min(half_range[2]) / largest
+    }
+
+    fn half_ranges(&self) -> [f32; 3] {
+        let mut half_range = [0.0; 3];
+        for (axis, range) in half_range.iter_mut().enumerate() {
+            *range = (self.max[axis] as f32 - self.min[axis] as f32) / 2.0;
+        }
+        half_range
+    }
+}
+
+impl Default for MagCalibrator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// LIS3MDL driver over any RegisterBus, see new (I2C) and new_spi
+pub struct Lis3mdl<BUS> {
+    regs: BUS,
     full_scale: FullScale,
+    ctrl_reg1: u8,        // Last value written to CTRL_REG1
+    temp_offset_c: f32,   // Temperature at a raw reading of 0
+    temp_lsb_per_c: f32,  // Temperature sensitivity
+    axis_map: AxisMap,    // Sensor to output frame mapping
+    offsets: [f32; 3],    // Hard-iron offsets in raw LSB
+    scales: [f32; 3],     // Soft-iron per-axis scale factors
+    lenient_who_am_i: bool, // Continue init_with_retries on a WHO_AM_I mismatch
+    temp_coeff_ppm_per_c: f32, // Sensitivity drift applied by the gauss readings
+    mount_rotation: [f32; 4],  // Sensor to vehicle frame unit quaternion [w, x, y, z], used by read_ned
 }
 
-impl<I2C, E> Lis3mdl<I2C>
-where
-    I2C: I2c<Error = E>,
-{
+// LIS3MDL on 4-wire SPI
+pub type Lis3mdlSpi<SPI> = Lis3mdl<SpiRegisters<SPI>>;
+
+// LIS3MDL on an I2C bus shared through a RefCell, see new_shared
+pub type SharedLis3mdl<'a, I2C> = Lis3mdl<I2cRegisters<RefCellDevice<'a, I2C>>>;
+
+impl<I2C: I2c> Lis3mdl<I2cRegisters<I2C>> {
     pub fn new(i2c: I2C) -> Self {
+        Self::new_with_address(i2c, LIS3MDL_ADDRESS)
+    }
+
+    /// Driver at a specific address, LIS3MDL_ADDRESS_ALT for a part with SA1 high
+    pub fn new_with_address(i2c: I2C, address: u8) -> Self {
+        Self::with_bus(I2cRegisters::new(i2c, address, ST_AUTO_INCREMENT))
+    }
+}
+
+impl<'a, I2C: I2c> SharedLis3mdl<'a, I2C> {
+    /// Driver borrowing a bus that other devices use too
+    ///
+    /// Each transaction borrows the RefCell, so drivers on the same bus must
+    /// not be used from an interrupt that can preempt another one mid-read.
+    ///
+    /// ```ignore
+    /// let bus = RefCell::new(hardware.i2c);
+    /// let (mut near, mut far) = lis3mdl::shared_pair(&bus);
+    /// near.init()?;
+    /// far.init()?;
+    /// let gradient = lis3mdl::read_gradient(&mut near, &mut far, 0.10)?;
+    /// ```
+    pub fn new_shared(bus: &'a RefCell<I2C>, address: u8) -> Self {
+        Self::new_with_address(RefCellDevice::new(bus), address)
+    }
+}
+
+/// Two LIS3MDLs on one bus, at LIS3MDL_ADDRESS and LIS3MDL_ADDRESS_ALT
+pub fn shared_pair<I2C: I2c>(bus: &RefCell<I2C>) -> (SharedLis3mdl<'_, I2C>, SharedLis3mdl<'_, I2C>) {
+    (
+        Lis3mdl::new_shared(bus, LIS3MDL_ADDRESS),
+        Lis3mdl::new_shared(bus, LIS3MDL_ADDRESS_ALT),
+    )
+}
+
+/// Field gradient in gauss per meter between two readings `baseline_m` apart
+///
+/// Per axis (b - a) / baseline, so the sign points from sensor a towards b.
+/// Both readings must be in the same frame; a non-positive baseline gives zero.
+pub fn field_gradient(a: (f32, f32, f32), b: (f32, f32, f32), baseline_m: f32) -> (f32, f32, f32) {
+    if baseline_m <= 0.0 {
+        return (0.0, 0.0, 0.0);
+    }
+    ((b.0 - a.0) / baseline_m, (b.1 - a.1) / baseline_m, (b.2 - a.2) / baseline_m)
+}
+
+/// Read both magnetometers in gauss (calibration applied) and return field_gradient
+/// The two reads are back to back, not simultaneous, so a fast-changing field adds error
+pub fn read_gradient<BUS: RegisterBus>(a: &mut Lis3mdl<BUS>, b: &mut Lis3mdl<BUS>, baseline_m: f32) -> Result<(f32, f32, f32), Error<BUS::Error>> {
+    let field_a = a.read_magnetometer_gauss()?;
+    let field_b = b.read_magnetometer_gauss()?;
+    Ok(field_gradient(field_a, field_b, baseline_m))
+}
+
+impl<SPI: SpiDevice> Lis3mdl<SpiRegisters<SPI>> {
+    /// Driver on 4-wire SPI; the `SpiDevice` owns chip select
+    /// The LIS3MDL needs SPI mode 3 (CPOL = 1, CPHA = 1) at up to 10 MHz
+    pub fn new_spi(spi: SPI) -> Self {
+        Self::with_bus(SpiRegisters::new(spi))
+    }
+}
+
+impl<BUS: RegisterBus> Lis3mdl<BUS> {
+    pub fn with_bus(regs: BUS) -> Self {
         Self {
-            i2c,
-            address: LIS3MDL_ADDRESS,
+            regs,
             full_scale: FullScale::Gauss4,
+            ctrl_reg1: 0,
+            temp_offset_c: TEMP_DEFAULT_OFFSET_C,
+            temp_lsb_per_c: TEMP_DEFAULT_LSB_PER_C,
+            axis_map: AXIS_MAP_IDENTITY,
+            offsets: [0.0; 3],
+            scales: [1.0; 3],
+            lenient_who_am_i: false,
+            temp_coeff_ppm_per_c: 0.0,
+            mount_rotation: QUATERNION_IDENTITY,
         }
     }
 
-    pub fn init(&mut self) -> Result<(), E> {
+    /// Let init_with_retries continue after a WHO_AM_I mismatch, like init
+    /// does, for clones that report a different ID
+    pub fn set_lenient_who_am_i(&mut self, lenient: bool) {
+        self.lenient_who_am_i = lenient;
+    }
+
+    /// Set the calibration applied by the gauss readings, e.g. from MagCalibrator::finish
+    /// Each axis becomes (raw - offset) * scale before conversion to gauss
+    pub fn set_calibration(&mut self, offsets: [f32; 3], scales: [f32; 3]) {
+        self.offsets = offsets;
+        self.scales = scales;
+    }
+
+    /// Set the axis remap applied by read_magnetometer
+    ///
+    /// Entry i gives the sensor axis feeding output axis i and whether it is
+    /// negated. A board rotated 90° about Z reads
+    /// `[(Axis::Y, false), (Axis::X, true), (Axis::Z, false)]`, a mirror in X
+    /// reads `[(Axis::X, true), (Axis::Y, false), (Axis::Z, false)]`.
+    pub fn set_axis_map(&mut self, map: AxisMap) {
+        self.axis_map = map;
+    }
+
+    /// Set the sensor-to-vehicle mounting rotation used by read_ned
+    ///
+    /// `q` is a quaternion [w, x, y, z] rotating vectors from the sensor frame
+    /// (after any axis map) into the vehicle/NED frame. Unlike the axis map it
+    /// handles mounts that are not a multiple of 90°. It is normalized here,
+    /// so small rounding in a hand-entered value does nothing; an all-zero
+    /// quaternion falls back to the identity.
+    pub fn set_mount_rotation(&mut self, q: [f32; 4]) {
+        let norm = libm::sqrtf(q.iter().map(|c| c * c).sum());
+        self.mount_rotation = if norm > 0.0 {
+            q.map(|c| c / norm)
+        } else {
+            QUATERNION_IDENTITY
+        };
+    }
+
+    /// Set the per-unit temperature calibration
+    /// `offset_c` is the temperature at a raw reading of 0, `lsb_per_c` the sensitivity
+    pub fn set_temp_calibration(&mut self, offset_c: f32, lsb_per_c: f32) {
+        self.temp_offset_c = offset_c;
+        self.temp_lsb_per_c = lsb_per_c;
+    }
+
+    /// Set the sensitivity temperature coefficient applied by the gauss readings
+    ///
+    /// Each axis is multiplied by `1 + ppm_per_c * 1e-6 * (temp_c - 25)`, using
+    /// the temperature read in the same burst as the axes. The default of 0
+    /// disables the correction, as does a disabled temperature sensor.
+    pub fn set_temp_coefficient(&mut self, ppm_per_c: f32) {
+        self.temp_coeff_ppm_per_c = ppm_per_c;
+    }
+
+    pub fn init(&mut self) -> Result<(), Error<BUS::Error>> {
         // Check WHO_AM_I register
         let who_am_i = self.read_register(WHO_AM_I)?;
         if who_am_i != LIS3MDL_WHO_AM_I_VALUE {
-            rprintln!("ERROR: LIS3MDL WHO_AM_I mismatch! Expected 0x{:02X}, got 0x{:02X}", 
+            sensor_log!("ERROR: LIS3MDL WHO_AM_I mismatch! Expected 0x{:02X}, got 0x{:02X}", 
                      LIS3MDL_WHO_AM_I_VALUE, who_am_i);
             // For now, we'll continue anyway - some clones might have different WHO_AM_I
         } else {
-            rprintln!("LIS3MDL WHO_AM_I check passed: 0x{:02X}", who_am_i);
+            sensor_log!("LIS3MDL WHO_AM_I check passed: 0x{:02X}", who_am_i);
         }
 
+        self.configure()
+    }
+
+    /// Initialize, retrying the WHO_AM_I check while the sensor boots
+    ///
+    /// After power-up the LIS3MDL may NACK or return garbage for a few
+    /// milliseconds. WHO_AM_I is read up to `retries + 1` times, waiting
+    /// WHO_AM_I_RETRY_DELAY_MS before the first retry and doubling the wait
+    /// each time. Fails with the last I2C error, or with Error::WhoAmIMismatch
+    /// unless set_lenient_who_am_i is on.
+    pub fn init_with_retries(&mut self, retries: u8, delay: &mut impl DelayNs) -> Result<(), Error<BUS::Error>> {
+        let mut wait_ms = WHO_AM_I_RETRY_DELAY_MS;
+        let mut attempt = 0;
+        let result = loop {
+            let result = self.read_register(WHO_AM_I);
+            if matches!(result, Ok(LIS3MDL_WHO_AM_I_VALUE)) || attempt == retries {
+                break result;
+            }
+            attempt += 1;
+            delay.delay_ms(wait_ms);
+            wait_ms = wait_ms.saturating_mul(2);
+        };
+
+        match result? {
+            LIS3MDL_WHO_AM_I_VALUE => {
+                sensor_log!("LIS3MDL WHO_AM_I check passed after {} retries", attempt);
+            }
+            who_am_i if self.lenient_who_am_i => {
+                sensor_log!("LIS3MDL WHO_AM_I mismatch (0x{:02X}), continuing", who_am_i);
+            }
+            who_am_i => return Err(Error::WhoAmIMismatch(who_am_i)),
+        }
+
+        self.configure()
+    }
+
+    /// Write the default register configuration
+    fn configure(&mut self) -> Result<(), Error<BUS::Error>> {
         // Configure CTRL_REG1: Temperature enabled, High performance XY, 10 Hz, no self-test
         // Bit 7: TEMP_EN = 1 (temperature sensor enabled)
         // Bit 6-5: OM[1:0] = 11 (High performance mode for X and Y axes)
@@ -99,6 +770,7 @@ where
         // Bit 1: FAST_ODR = 0
         // Bit 0: ST = 0 (self-test disabled)
         self.write_register(CTRL_REG1, 0b11101000)?;
+        self.ctrl_reg1 = 0b11101000;
 
         // Configure CTRL_REG2: Full scale ±4 gauss, no reset
         // Bit 7: Reserved = 0
@@ -127,30 +799,90 @@ where
         // Bit 5-0: Reserved = 000000
         self.write_register(CTRL_REG5, 0b00000000)?;
 
-        rprintln!("LIS3MDL initialized successfully");
+        sensor_log!("LIS3MDL initialized successfully");
         Ok(())
     }
 
-    pub fn read_magnetometer(&mut self) -> Result<MagnetometerData, E> {
+    /// Change the output data rate and performance mode of all three axes
+    ///
+    /// The FAST_ODR rates (155 Hz and up) are tied to a performance mode and
+    /// override `mode`, see DataRate::performance_mode; e.g. 300 Hz always
+    /// runs in high performance mode. The Z axis (CTRL_REG4 OMZ) is set to
+    /// the same mode as X/Y. Temperature and self-test bits are kept.
+    pub fn set_data_rate(&mut self, rate: DataRate, mode: PerformanceMode) -> Result<(), Error<BUS::Error>> {
+        let ctrl_reg1 = (self.ctrl_reg1 & CTRL_REG1_KEEP_MASK) | rate.ctrl_reg1_bits(mode);
+        self.write_register(CTRL_REG1, ctrl_reg1)?;
+        self.ctrl_reg1 = ctrl_reg1;
+
+        let omz = rate.performance_mode(mode).bits() << 2;
+        let ctrl_reg4 = self.read_register(CTRL_REG4)?;
+        self.write_register(CTRL_REG4, (ctrl_reg4 & !CTRL_REG4_OMZ_MASK) | omz)
+    }
+
+    /// Poll STATUS_REG until a new X/Y/Z sample is ready
+    /// Fails with Error::SensorTimeout after `timeout_ms`
+    pub fn wait_data_ready(&mut self, timeout_ms: u32, delay: &mut impl DelayNs) -> Result<(), Error<BUS::Error>> {
+        for _ in 0..=timeout_ms {
+            if self.read_register(STATUS_REG)? & STATUS_ZYXDA != 0 {
+                return Ok(());
+            }
+            delay.delay_ms(1);
+        }
+        Err(Error::SensorTimeout)
+    }
+
+    /// Capture up to `out.len()` consecutive samples, waiting on data-ready for each
+    ///
+    /// The LIS3MDL has no FIFO, so this polls STATUS_REG and reads each sample
+    /// as it lands; samples are spaced by the configured output data rate.
+    /// Returns how many were captured before data-ready failed to come up
+    /// within two sample periods.
+    pub fn read_batch(&mut self, out: &mut [MagnetometerData], delay: &mut impl DelayNs) -> Result<usize, Error<BUS::Error>> {
+        let timeout_ms = 2 * self.sample_period_ms();
+        for (count, sample) in out.iter_mut().enumerate() {
+            match self.wait_data_ready(timeout_ms, delay) {
+                Ok(()) => {}
+                Err(Error::SensorTimeout) => return Ok(count),
+                Err(e) => return Err(e),
+            }
+            *sample = self.read_all()?;
+        }
+        Ok(out.len())
+    }
+
+    /// Output data period from the last CTRL_REG1 written, rounded up to whole ms
+    fn sample_period_ms(&self) -> u32 {
+        if self.ctrl_reg1 & CTRL_REG1_FAST_ODR != 0 {
+            return FAST_ODR_PERIOD_MS[((self.ctrl_reg1 >> 5) & 0b11) as usize];
+        }
+        ODR_PERIOD_MS[((self.ctrl_reg1 >> 2) & 0x07) as usize]
+    }
+
+    pub fn read_magnetometer(&mut self) -> Result<MagnetometerData, Error<BUS::Error>> {
         // Check if data is ready
         let status = self.read_register(STATUS_REG)?;
-        if (status & 0x08) == 0 {
-            rprintln!("Warning: Data not ready yet (STATUS: 0x{:02X})", status);
+        if (status & STATUS_ZYXDA) == 0 {
+            sensor_log!("Warning: Data not ready yet (STATUS: 0x{:02X})", status);
         }
 
-        // Read all magnetometer data (6 bytes) in one go
-        let mut data = [0u8; 6];
+        self.read_all()
+    }
+
+    /// Read X/Y/Z and temperature in a single 8-byte burst
+    /// TEMP_OUT_L/H (0x2E/0x2F) directly follow OUT_Z_H, so one
+    /// auto-incrementing read from OUT_X_L covers both
+    pub fn read_all(&mut self) -> Result<MagnetometerData, Error<BUS::Error>> {
+        let mut data = [0u8; 8];
         self.read_registers(OUT_X_L, &mut data)?;
 
         // Convert to signed 16-bit values (little endian)
         let x = i16::from_le_bytes([data[0], data[1]]);
         let y = i16::from_le_bytes([data[2], data[3]]);
         let z = i16::from_le_bytes([data[4], data[5]]);
+        let temperature = i16::from_le_bytes([data[6], data[7]]);
 
-        // Read temperature (2 bytes)
-        let mut temp_data = [0u8; 2];
-        self.read_registers(TEMP_OUT_L, &mut temp_data)?;
-        let temperature = i16::from_le_bytes([temp_data[0], temp_data[1]]);
+        // Rotate/mirror into the vehicle frame
+        let [x, y, z] = apply_axis_map(&self.axis_map, [x, y, z]);
 
         Ok(MagnetometerData {
             x,
@@ -160,9 +892,57 @@ where
         })
     }
 
-    pub fn read_magnetometer_gauss(&mut self) -> Result<(f32, f32, f32), E> {
+    pub fn read_magnetometer_gauss(&mut self) -> Result<(f32, f32, f32), Error<BUS::Error>> {
         let data = self.read_magnetometer()?;
-        
+        Ok(self.to_gauss(&data))
+    }
+
+    /// One reading at a different full scale, leaving the configured one in place
+    ///
+    /// Sets the FS bits in CTRL_REG2, throws away the sample that was already
+    /// converting at the old range, waits for data-ready and reads, then
+    /// restores the previous register value. That costs up to two extra sample
+    /// periods of latency (200 ms at the default 10 Hz). The previous scale is
+    /// restored even if the read fails.
+    pub fn read_magnetometer_gauss_with(&mut self, fs: FullScale, delay: &mut impl DelayNs) -> Result<(f32, f32, f32), Error<BUS::Error>> {
+        let ctrl_reg2 = self.read_register(CTRL_REG2)?;
+        self.write_register(CTRL_REG2, (ctrl_reg2 & !CTRL_REG2_FS_MASK) | fs.ctrl_reg2_bits())?;
+
+        let configured = self.full_scale;
+        self.full_scale = fs;
+        let result = self.read_settled(delay).map(|data| self.to_gauss(&data));
+        self.full_scale = configured;
+
+        self.write_register(CTRL_REG2, ctrl_reg2)?;
+        result
+    }
+
+    /// Discard the current sample and read the next one
+    fn read_settled(&mut self, delay: &mut impl DelayNs) -> Result<MagnetometerData, Error<BUS::Error>> {
+        let timeout_ms = 2 * self.sample_period_ms();
+        self.read_all()?;
+        self.wait_data_ready(timeout_ms, delay)?;
+        self.read_all()
+    }
+
+    /// Calibrated field in gauss, rotated into the vehicle frame by set_mount_rotation
+    pub fn read_ned(&mut self) -> Result<(f32, f32, f32), Error<BUS::Error>> {
+        let (x, y, z) = self.read_magnetometer_gauss()?;
+        let [n, e, d] = rotate_by_quaternion(self.mount_rotation, [x, y, z]);
+        Ok((n, e, d))
+    }
+
+    /// Same as read_magnetometer_gauss, but fails with Error::Saturated
+    /// instead of returning clipped values
+    pub fn read_magnetometer_gauss_checked(&mut self) -> Result<(f32, f32, f32), Error<BUS::Error>> {
+        let data = self.read_magnetometer()?;
+        if data.is_saturated() {
+            return Err(Error::Saturated);
+        }
+        Ok(self.to_gauss(&data))
+    }
+
+    fn to_gauss(&self, data: &MagnetometerData) -> (f32, f32, f32) {
         // Convert raw values to gauss based on full scale setting
         let scale_factor = match self.full_scale {
             FullScale::Gauss4 => 4.0 / 32768.0,   // ±4 gauss, 16-bit
@@ -171,34 +951,619 @@ where
             FullScale::Gauss16 => 16.0 / 32768.0, // ±16 gauss, 16-bit
         };
 
-        let x_gauss = data.x as f32 * scale_factor;
-        let y_gauss = data.y as f32 * scale_factor;
-        let z_gauss = data.z as f32 * scale_factor;
+        // Sensitivity drift relative to TEMP_COEFF_REFERENCE_C
+        let scale_factor = if self.temp_coeff_ppm_per_c != 0.0 && self.ctrl_reg1 & CTRL_REG1_TEMP_EN != 0 {
+            let temp_c = self.temp_offset_c + data.temperature as f32 / self.temp_lsb_per_c;
+            scale_factor * (1.0 + self.temp_coeff_ppm_per_c * 1e-6 * (temp_c - TEMP_COEFF_REFERENCE_C))
+        } else {
+            scale_factor
+        };
 
-        Ok((x_gauss, y_gauss, z_gauss))
+        let x_gauss = (data.x as f32 - self.offsets[0]) * self.scales[0] * scale_factor;
+        let y_gauss = (data.y as f32 - self.offsets[1]) * self.scales[1] * scale_factor;
+        let z_gauss = (data.z as f32 - self.offsets[2]) * self.scales[2] * scale_factor;
+
+        (x_gauss, y_gauss, z_gauss)
     }
 
-    pub fn read_temperature_celsius(&mut self) -> Result<f32, E> {
-        let data = self.read_magnetometer()?;
+    /// Read only the raw temperature output (TEMP_OUT_L/H), skipping the axes
+    pub fn read_temperature_raw(&mut self) -> Result<i16, Error<BUS::Error>> {
+        let mut temp_data = [0u8; 2];
+        self.read_registers(TEMP_OUT_L, &mut temp_data)?;
+        Ok(i16::from_le_bytes([temp_data[0], temp_data[1]]))
+    }
+
+    pub fn read_temperature_celsius(&mut self) -> Result<f32, Error<BUS::Error>> {
+        if self.ctrl_reg1 & CTRL_REG1_TEMP_EN == 0 {
+            return Err(Error::TemperatureDisabled);
+        }
+
+        let temperature = self.read_temperature_raw()?;
         
-        // Temperature calculation: 25°C + (TEMP_OUT / 256)
-        // The offset might need calibration for your specific sensor
-        let temp_celsius = 25.0 + (data.temperature as f32 / 256.0);
+        // Temperature calculation: offset + (TEMP_OUT / sensitivity)
+        // Defaults to the datasheet 25°C + TEMP_OUT / 8, see set_temp_calibration
+        let temp_celsius = self.temp_offset_c + (temperature as f32 / self.temp_lsb_per_c);
         
         Ok(temp_celsius)
     }
 
-    fn write_register(&mut self, register: u8, value: u8) -> Result<(), E> {
-        self.i2c.write(self.address, &[register, value])
+    /// Configure the INT pin to fire when any selected axis exceeds `threshold`
+    ///
+    /// `axes` is a mask of `INT_X_AXIS | INT_Y_AXIS | INT_Z_AXIS`; an empty mask
+    /// disables the interrupt. The threshold is an unsigned 15-bit magnitude in
+    /// raw LSB, compared against the absolute value of each axis. A latched
+    /// interrupt holds the pin until INT_SRC is read.
+    pub fn configure_interrupt(&mut self, axes: u8, threshold: u16, active_high: bool, latch: bool) -> Result<(), Error<BUS::Error>> {
+        let threshold = threshold & 0x7FFF;
+        self.write_register(INT_THS_L, threshold as u8)?;
+        self.write_register(INT_THS_H, (threshold >> 8) as u8)?;
+
+        let mut int_cfg = (axes & (INT_X_AXIS | INT_Y_AXIS | INT_Z_AXIS)) | INT_CFG_RESERVED;
+        if active_high {
+            int_cfg |= INT_CFG_IEA;
+        }
+        if !latch {
+            int_cfg |= INT_CFG_LIR;
+        }
+        if int_cfg & (INT_X_AXIS | INT_Y_AXIS | INT_Z_AXIS) != 0 {
+            int_cfg |= INT_CFG_IEN;
+        }
+
+        // Enable last so the pin can't fire on a stale threshold
+        self.write_register(INT_CFG, int_cfg)
+    }
+
+    /// Read CTRL_REG1..CTRL_REG5 in one burst, for debugging the live configuration
+    /// Pass the result to ControlRegs::from_bytes for a decoded, Debug-printable view
+    pub fn dump_control_registers(&mut self) -> Result<[u8; 5], Error<BUS::Error>> {
+        let mut regs = [0u8; 5];
+        self.read_registers(CTRL_REG1, &mut regs)?;
+        Ok(regs)
+    }
+
+    /// Read back the programmed interrupt configuration
+    /// INT_SRC sits between INT_CFG and INT_THS and is cleared on read, so the
+    /// registers are read in two transactions that skip it
+    pub fn get_interrupt_config(&mut self) -> Result<InterruptConfig, Error<BUS::Error>> {
+        let int_cfg = self.read_register(INT_CFG)?;
+        let mut ths = [0u8; 2];
+        self.read_registers(INT_THS_L, &mut ths)?;
+
+        Ok(InterruptConfig {
+            axes: int_cfg & (INT_X_AXIS | INT_Y_AXIS | INT_Z_AXIS),
+            threshold: u16::from_le_bytes(ths) & 0x7FFF,
+            active_high: int_cfg & INT_CFG_IEA != 0,
+            latch: int_cfg & INT_CFG_LIR == 0,
+        })
+    }
+
+    /// Read INT_SRC (also clears a latched interrupt)
+    /// Bits: 7 PTH_X, 6 PTH_Y, 5 PTH_Z, 4 NTH_X, 3 NTH_Y, 2 NTH_Z, 1 MROI, 0 INT
+    pub fn read_interrupt_source(&mut self) -> Result<u8, Error<BUS::Error>> {
+        self.read_register(INT_SRC)
+    }
+
+    fn write_register(&mut self, register: u8, value: u8) -> Result<(), Error<BUS::Error>> {
+        self.regs.write_reg(register, value)
+    }
+
+    fn read_register(&mut self, register: u8) -> Result<u8, Error<BUS::Error>> {
+        self.regs.read_reg(register)
+    }
+
+    fn read_registers(&mut self, start_register: u8, buffer: &mut [u8]) -> Result<(), Error<BUS::Error>> {
+        self.regs.read_regs(start_register, buffer)
+    }
+}
+
+impl<BUS: RegisterBus> Magnetometer for Lis3mdl<BUS> {
+    type Error = Error<BUS::Error>;
+
+    fn read(&mut self) -> Result<MagnetometerData, Error<BUS::Error>> {
+        self.read_magnetometer()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_hal_mock::eh1::delay::NoopDelay;
+    use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction};
+
+    // Register writes done by init, in order
+    fn init_transactions() -> Vec<Transaction> {
+        vec![
+            Transaction::write_read(LIS3MDL_ADDRESS, vec![WHO_AM_I], vec![LIS3MDL_WHO_AM_I_VALUE]),
+            Transaction::write(LIS3MDL_ADDRESS, vec![CTRL_REG1, 0b1110_1000]),
+            Transaction::write(LIS3MDL_ADDRESS, vec![CTRL_REG2, 0x00]),
+            Transaction::write(LIS3MDL_ADDRESS, vec![CTRL_REG3, 0x00]),
+            Transaction::write(LIS3MDL_ADDRESS, vec![CTRL_REG4, 0b0000_1100]),
+            Transaction::write(LIS3MDL_ADDRESS, vec![CTRL_REG5, 0x00]),
+        ]
+    }
+
+    #[test]
+    fn init_writes_control_registers() {
+        let mut i2c = I2cMock::new(&init_transactions());
+        let mut mag = Lis3mdl::new(i2c.clone());
+        mag.init().unwrap();
+        i2c.done();
+    }
+
+    #[test]
+    fn read_magnetometer_bursts_xyz_and_temperature() {
+        let mut expectations = init_transactions();
+        expectations.extend([
+            Transaction::write_read(LIS3MDL_ADDRESS, vec![STATUS_REG], vec![0x08]),
+            Transaction::write_read(
+                LIS3MDL_ADDRESS,
+                vec![OUT_X_L | ST_AUTO_INCREMENT],
+                vec![0x00, 0x20, 0x00, 0xE0, 0x00, 0x40, 0x50, 0x00],
+            ),
+        ]);
+        let mut i2c = I2cMock::new(&expectations);
+        let mut mag = Lis3mdl::new(i2c.clone());
+        mag.init().unwrap();
+
+        // 0x2000 = 8192 LSB = 1 gauss at ±4 gauss
+        let (x, y, z) = mag.read_magnetometer_gauss().unwrap();
+        assert_eq!((x, y, z), (1.0, -1.0, 2.0));
+        i2c.done();
+    }
+
+    #[test]
+    fn sample_period_follows_fast_odr_performance_mode() {
+        let cases = [
+            (DataRate::Hz1000, 1),
+            (DataRate::Hz560, 2),
+            (DataRate::Hz300, 4),
+            (DataRate::Hz155, 7),
+            (DataRate::Hz80, 13),
+            (DataRate::Hz0_625, 1600),
+        ];
+        for (rate, expected) in cases {
+            let mut mag = Lis3mdl::new(I2cMock::new(&[]));
+            mag.ctrl_reg1 = rate.ctrl_reg1_bits(PerformanceMode::UltraHigh);
+            assert_eq!(mag.sample_period_ms(), expected, "{:?}", rate);
+            mag.regs.release().done();
+        }
+    }
+
+    #[test]
+    fn temperature_uses_offset_and_sensitivity() {
+        let mut expectations = init_transactions();
+        for raw in [80i16, -16] {
+            expectations.push(Transaction::write_read(
+                LIS3MDL_ADDRESS,
+                vec![TEMP_OUT_L | ST_AUTO_INCREMENT],
+                raw.to_le_bytes().to_vec(),
+            ));
+        }
+        let mut i2c = I2cMock::new(&expectations);
+        let mut mag = Lis3mdl::new(i2c.clone());
+        assert!(matches!(mag.read_temperature_celsius(), Err(Error::TemperatureDisabled)));
+        mag.init().unwrap();
+
+        // Datasheet default: 25°C + TEMP_OUT / 8
+        assert_eq!(mag.read_temperature_celsius().unwrap(), 35.0);
+        mag.set_temp_calibration(20.0, 4.0);
+        assert_eq!(mag.read_temperature_celsius().unwrap(), 16.0);
+        i2c.done();
+    }
+
+    // Traffic of read_magnetometer_gauss_with: switch FS in CTRL_REG2 (0x00
+    // before), discard a sample, wait for data-ready, read `sample`, restore
+    fn gauss_with_transactions(fs: FullScale, sample: [u8; 8]) -> Vec<Transaction> {
+        vec![
+            Transaction::write_read(LIS3MDL_ADDRESS, vec![CTRL_REG2], vec![0x00]),
+            Transaction::write(LIS3MDL_ADDRESS, vec![CTRL_REG2, fs.ctrl_reg2_bits()]),
+            Transaction::write_read(LIS3MDL_ADDRESS, vec![OUT_X_L | ST_AUTO_INCREMENT], vec![0; 8]),
+            Transaction::write_read(LIS3MDL_ADDRESS, vec![STATUS_REG], vec![STATUS_ZYXDA]),
+            Transaction::write_read(LIS3MDL_ADDRESS, vec![OUT_X_L | ST_AUTO_INCREMENT], sample.to_vec()),
+            Transaction::write(LIS3MDL_ADDRESS, vec![CTRL_REG2, 0x00]),
+        ]
+    }
+
+    #[test]
+    fn gauss_conversion_at_each_full_scale() {
+        // x = 16384, y = -16384, z = 0: half of the range on x and y
+        let sample = [0x00, 0x40, 0x00, 0xC0, 0x00, 0x00, 0x00, 0x00];
+        let cases = [
+            (FullScale::Gauss4, 2.0),
+            (FullScale::Gauss8, 4.0),
+            (FullScale::Gauss12, 6.0),
+            (FullScale::Gauss16, 8.0),
+        ];
+        for (full_scale, expected) in cases {
+            let mut i2c = I2cMock::new(&gauss_with_transactions(full_scale, sample));
+            let mut mag = Lis3mdl::new(i2c.clone());
+            let gauss = mag.read_magnetometer_gauss_with(full_scale, &mut NoopDelay).unwrap();
+            assert_eq!(gauss, (expected, -expected, 0.0), "{:?}", full_scale);
+            i2c.done();
+        }
+    }
+
+    #[test]
+    fn shared_pair_reads_a_gradient_over_one_bus() {
+        let burst = |address, x: i16| {
+            let [lo, hi] = x.to_le_bytes();
+            [
+                Transaction::write_read(address, vec![STATUS_REG], vec![STATUS_ZYXDA]),
+                Transaction::write_read(address, vec![OUT_X_L | ST_AUTO_INCREMENT], vec![lo, hi, 0, 0, 0, 0, 0, 0]),
+            ]
+        };
+        // 1 gauss on x at the default address, 2 gauss at the alternate one
+        let mut expectations = burst(LIS3MDL_ADDRESS, 8192).to_vec();
+        expectations.extend(burst(LIS3MDL_ADDRESS_ALT, 16384));
+        let bus = RefCell::new(I2cMock::new(&expectations));
+
+        let (mut near, mut far) = shared_pair(&bus);
+        let gradient = read_gradient(&mut near, &mut far, 0.5).unwrap();
+        assert_eq!(gradient, (2.0, 0.0, 0.0));
+        bus.borrow_mut().done();
+    }
+
+    #[test]
+    fn gauss_with_restores_ctrl_reg2_when_the_read_fails() {
+        use embedded_hal::i2c::ErrorKind;
+
+        let burst = vec![OUT_X_L | ST_AUTO_INCREMENT];
+        let mut i2c = I2cMock::new(&[
+            // Other CTRL_REG2 bits survive the switch, only FS changes
+            Transaction::write_read(LIS3MDL_ADDRESS, vec![CTRL_REG2], vec![0x20]),
+            Transaction::write(LIS3MDL_ADDRESS, vec![CTRL_REG2, FullScale::Gauss16.ctrl_reg2_bits()]),
+            Transaction::write_read(LIS3MDL_ADDRESS, burst.clone(), vec![0; 8]),
+            Transaction::write_read(LIS3MDL_ADDRESS, vec![STATUS_REG], vec![STATUS_ZYXDA]),
+            Transaction::write_read(LIS3MDL_ADDRESS, burst.clone(), vec![0; 8]).with_error(ErrorKind::Other),
+            Transaction::write(LIS3MDL_ADDRESS, vec![CTRL_REG2, 0x20]),
+            // The next plain read converts at the configured ±4 gauss again
+            Transaction::write_read(LIS3MDL_ADDRESS, vec![STATUS_REG], vec![STATUS_ZYXDA]),
+            Transaction::write_read(LIS3MDL_ADDRESS, burst, vec![0x00, 0x20, 0, 0, 0, 0, 0, 0]),
+        ]);
+        let mut mag = Lis3mdl::new(i2c.clone());
+
+        let result = mag.read_magnetometer_gauss_with(FullScale::Gauss16, &mut NoopDelay);
+        assert_eq!(result, Err(Error::I2c(ErrorKind::Other)));
+        assert_eq!(mag.read_magnetometer_gauss().unwrap(), (1.0, 0.0, 0.0));
+        i2c.done();
     }
 
-    fn read_register(&mut self, register: u8) -> Result<u8, E> {
-        let mut buffer = [0u8; 1];
-        self.i2c.write_read(self.address, &[register], &mut buffer)?;
-        Ok(buffer[0])
+    #[test]
+    fn read_ned_applies_the_mount_rotation() {
+        // 1 gauss along the sensor's x axis on every read
+        let read = [
+            Transaction::write_read(LIS3MDL_ADDRESS, vec![STATUS_REG], vec![STATUS_ZYXDA]),
+            Transaction::write_read(LIS3MDL_ADDRESS, vec![OUT_X_L | ST_AUTO_INCREMENT], vec![0x00, 0x20, 0, 0, 0, 0, 0, 0]),
+        ];
+        let mut i2c = I2cMock::new(&[read.clone(), read].concat());
+        let mut mag = Lis3mdl::new(i2c.clone());
+
+        // 90° about z, not normalized: x ends up on y
+        mag.set_mount_rotation([1.0, 0.0, 0.0, 1.0]);
+        let (n, e, d) = mag.read_ned().unwrap();
+        assert!(n.abs() < 1e-6 && (e - 1.0).abs() < 1e-6 && d.abs() < 1e-6, "{:?}", (n, e, d));
+
+        // An all-zero quaternion falls back to the identity
+        mag.set_mount_rotation([0.0; 4]);
+        assert_eq!(mag.read_ned().unwrap(), (1.0, 0.0, 0.0));
+        i2c.done();
     }
 
-    fn read_registers(&mut self, start_register: u8, buffer: &mut [u8]) -> Result<(), E> {
-        self.i2c.write_read(self.address, &[start_register], buffer)
+    #[test]
+    fn interrupt_config_round_trips() {
+        let cases = [
+            // Latched: LIR stays clear; bit 3 is always written as 1
+            (InterruptConfig { axes: INT_X_AXIS | INT_Z_AXIS, threshold: 0x0123, active_high: true, latch: true }, 0b1010_1101),
+            // Not latched sets LIR, active low leaves IEA clear
+            (InterruptConfig { axes: INT_Y_AXIS, threshold: 0x7FFF, active_high: false, latch: false }, 0b0100_1011),
+        ];
+        for (config, int_cfg) in cases {
+            let [ths_l, ths_h] = config.threshold.to_le_bytes();
+            let mut i2c = I2cMock::new(&[
+                Transaction::write(LIS3MDL_ADDRESS, vec![INT_THS_L, ths_l]),
+                Transaction::write(LIS3MDL_ADDRESS, vec![INT_THS_H, ths_h]),
+                Transaction::write(LIS3MDL_ADDRESS, vec![INT_CFG, int_cfg]),
+                Transaction::write_read(LIS3MDL_ADDRESS, vec![INT_CFG], vec![int_cfg]),
+                Transaction::write_read(LIS3MDL_ADDRESS, vec![INT_THS_L | ST_AUTO_INCREMENT], vec![ths_l, ths_h]),
+            ]);
+            let mut mag = Lis3mdl::new(i2c.clone());
+
+            mag.configure_interrupt(config.axes, config.threshold, config.active_high, config.latch).unwrap();
+            assert_eq!(mag.get_interrupt_config().unwrap(), config);
+            i2c.done();
+        }
+    }
+
+    #[test]
+    fn set_data_rate_writes_fast_odr_bits() {
+        // CTRL_REG1 keeps TEMP_EN from init, OM comes from the rate whatever
+        // mode was asked for, DO is cleared and FAST_ODR set; OMZ follows OM
+        let cases = [
+            (DataRate::Hz1000, 0b1000_0010, 0b0000_0000),
+            (DataRate::Hz560, 0b1010_0010, 0b0000_0100),
+            (DataRate::Hz300, 0b1100_0010, 0b0000_1000),
+            (DataRate::Hz155, 0b1110_0010, 0b0000_1100),
+        ];
+        for (rate, ctrl_reg1, ctrl_reg4) in cases {
+            let mut expectations = init_transactions();
+            expectations.extend([
+                Transaction::write(LIS3MDL_ADDRESS, vec![CTRL_REG1, ctrl_reg1]),
+                Transaction::write_read(LIS3MDL_ADDRESS, vec![CTRL_REG4], vec![0b0000_1100]),
+                Transaction::write(LIS3MDL_ADDRESS, vec![CTRL_REG4, ctrl_reg4]),
+            ]);
+            let mut i2c = I2cMock::new(&expectations);
+            let mut mag = Lis3mdl::new(i2c.clone());
+            mag.init().unwrap();
+
+            mag.set_data_rate(rate, PerformanceMode::UltraHigh).unwrap();
+            i2c.done();
+        }
+    }
+
+    fn sample(x: i16, y: i16, z: i16) -> MagnetometerData {
+        MagnetometerData { x, y, z, temperature: 0 }
+    }
+
+    #[test]
+    fn mag_filter_averages_steady_and_ramp_inputs() {
+        let mut filter: MagFilter<4> = MagFilter::new();
+        assert_eq!(filter.average(), (0.0, 0.0, 0.0));
+
+        // Steady input at the rail averages to itself without overflowing
+        for _ in 0..10 {
+            filter.push(sample(i16::MAX, i16::MIN, 100));
+        }
+        assert_eq!(filter.average(), (i16::MAX as f32, i16::MIN as f32, 100.0));
+
+        // Ramp 0, 10, .., 90: after wraparound the window holds the last four
+        let mut filter: MagFilter<4> = MagFilter::new();
+        filter.push(sample(0, 0, 0));
+        assert_eq!(filter.average(), (0.0, 0.0, 0.0));
+        filter.push(sample(10, -10, 0));
+        assert_eq!(filter.average(), (5.0, -5.0, 0.0));
+        for step in 2..10 {
+            filter.push(sample(step * 10, -step * 10, 0));
+        }
+        assert_eq!(filter.average(), (75.0, -75.0, 0.0));
+    }
+
+    #[test]
+    fn read_temperature_raw_only_touches_temp_out() {
+        // One 2-byte burst of TEMP_OUT_L/H: no STATUS_REG check, no axis read
+        let mut i2c = I2cMock::new(&[Transaction::write_read(
+            LIS3MDL_ADDRESS,
+            vec![TEMP_OUT_L | ST_AUTO_INCREMENT],
+            vec![0x30, 0xFF],
+        )]);
+        let mut mag = Lis3mdl::new(i2c.clone());
+        assert_eq!(mag.read_temperature_raw().unwrap(), -208);
+        i2c.done();
+    }
+
+    #[test]
+    fn configure_interrupt_z_axis_at_2000_lsb() {
+        let mut i2c = I2cMock::new(&[
+            // Threshold first, INT_CFG last with ZIEN | reserved bit 3 | IEN
+            Transaction::write(LIS3MDL_ADDRESS, vec![INT_THS_L, 0xD0]),
+            Transaction::write(LIS3MDL_ADDRESS, vec![INT_THS_H, 0x07]),
+            Transaction::write(LIS3MDL_ADDRESS, vec![INT_CFG, 0b0010_1001]),
+            Transaction::write_read(LIS3MDL_ADDRESS, vec![INT_SRC], vec![0b0010_0001]),
+        ]);
+        let mut mag = Lis3mdl::new(i2c.clone());
+
+        mag.configure_interrupt(INT_Z_AXIS, 2000, false, true).unwrap();
+        // PTH_Z | INT
+        assert_eq!(mag.read_interrupt_source().unwrap(), 0b0010_0001);
+        i2c.done();
+    }
+
+    #[test]
+    fn saturation_at_the_rails() {
+        assert!(sample(i16::MAX, 0, 0).is_saturated());
+        assert!(sample(0, i16::MIN, 0).is_saturated());
+        assert!(sample(0, 0, i16::MAX - SATURATION_MARGIN).is_saturated());
+        assert!(!sample(i16::MAX - SATURATION_MARGIN - 1, i16::MIN + SATURATION_MARGIN + 1, 0).is_saturated());
+
+        // The checked read refuses the clipped sample, the plain one returns it
+        let read = [
+            Transaction::write_read(LIS3MDL_ADDRESS, vec![STATUS_REG], vec![STATUS_ZYXDA]),
+            Transaction::write_read(LIS3MDL_ADDRESS, vec![OUT_X_L | ST_AUTO_INCREMENT], vec![0xFF, 0x7F, 0, 0, 0, 0, 0, 0]),
+        ];
+        let mut i2c = I2cMock::new(&[read.clone(), read].concat());
+        let mut mag = Lis3mdl::new(i2c.clone());
+        assert_eq!(mag.read_magnetometer_gauss_checked(), Err(Error::Saturated));
+        assert!(mag.read_magnetometer_gauss().is_ok());
+        i2c.done();
+    }
+
+    #[test]
+    fn axis_map_rotates_and_mirrors_readings() {
+        // Raw x = 100, y = 200, z = i16::MIN on every read
+        let read = [
+            Transaction::write_read(LIS3MDL_ADDRESS, vec![STATUS_REG], vec![STATUS_ZYXDA]),
+            Transaction::write_read(LIS3MDL_ADDRESS, vec![OUT_X_L | ST_AUTO_INCREMENT], vec![100, 0, 200, 0, 0x00, 0x80, 0, 0]),
+        ];
+        let mut i2c = I2cMock::new(&[read.clone(), read.clone(), read].concat());
+        let mut mag = Lis3mdl::new(i2c.clone());
+        let xyz = |data: MagnetometerData| (data.x, data.y, data.z);
+
+        assert_eq!(xyz(mag.read_magnetometer().unwrap()), (100, 200, i16::MIN));
+
+        // Mounted rotated 90° about Z: vehicle X is sensor -Y, vehicle Y is sensor X
+        mag.set_axis_map([(Axis::Y, true), (Axis::X, false), (Axis::Z, false)]);
+        assert_eq!(xyz(mag.read_magnetometer().unwrap()), (-200, 100, i16::MIN));
+
+        // Mirrored in Z, which saturates instead of overflowing at i16::MIN
+        mag.set_axis_map([(Axis::X, false), (Axis::Y, false), (Axis::Z, true)]);
+        assert_eq!(xyz(mag.read_magnetometer().unwrap()), (100, 200, i16::MAX));
+        i2c.done();
+    }
+
+    #[test]
+    fn init_with_retries_waits_out_a_booting_sensor() {
+        use embedded_hal_mock::eh1::delay::{CheckedDelay, Transaction as DelayTransaction};
+
+        // Garbage twice, then the right ID and the usual configuration
+        let mut expectations = vec![
+            Transaction::write_read(LIS3MDL_ADDRESS, vec![WHO_AM_I], vec![0xFF]),
+            Transaction::write_read(LIS3MDL_ADDRESS, vec![WHO_AM_I], vec![0x00]),
+        ];
+        expectations.extend(init_transactions());
+        let mut i2c = I2cMock::new(&expectations);
+        // Backoff doubles from WHO_AM_I_RETRY_DELAY_MS
+        let mut delay = CheckedDelay::new(&[
+            DelayTransaction::delay_ms(WHO_AM_I_RETRY_DELAY_MS),
+            DelayTransaction::delay_ms(2 * WHO_AM_I_RETRY_DELAY_MS),
+        ]);
+        let mut mag = Lis3mdl::new(i2c.clone());
+
+        mag.init_with_retries(3, &mut delay).unwrap();
+        i2c.done();
+        delay.done();
+
+        // Out of retries on a wrong ID: strict by default
+        let mut i2c = I2cMock::new(&[
+            Transaction::write_read(LIS3MDL_ADDRESS, vec![WHO_AM_I], vec![0x33]),
+            Transaction::write_read(LIS3MDL_ADDRESS, vec![WHO_AM_I], vec![0x33]),
+        ]);
+        let mut mag = Lis3mdl::new(i2c.clone());
+        assert_eq!(mag.init_with_retries(1, &mut NoopDelay), Err(Error::WhoAmIMismatch(0x33)));
+        i2c.done();
+    }
+
+    #[test]
+    fn wait_data_ready_times_out() {
+        // One poll per millisecond, timeout included
+        let not_ready = Transaction::write_read(LIS3MDL_ADDRESS, vec![STATUS_REG], vec![0x00]);
+        let mut i2c = I2cMock::new(&[not_ready.clone(), not_ready.clone(), not_ready]);
+        let mut mag = Lis3mdl::new(i2c.clone());
+
+        assert_eq!(mag.wait_data_ready(2, &mut NoopDelay), Err(Error::SensorTimeout));
+        i2c.done();
+    }
+
+    #[test]
+    fn mag_calibrator_recovers_a_synthetic_ellipsoid() {
+        // Hard-iron center and soft-iron semi-axes, in LSB
+        let center = [100.0, -200.0, 50.0];
+        let semi_axes = [2000.0, 1500.0, 1000.0];
+        let point = |elevation_deg: f64, azimuth_deg: f64| {
+            let (el, az) = (elevation_deg.to_radians(), azimuth_deg.to_radians());
+            let unit = [el.cos() * az.cos(), el.cos() * az.sin(), el.sin()];
+            let axis = |i: usize| (center[i] + semi_axes[i] * unit[i]).round() as i16;
+            sample(axis(0), axis(1), axis(2))
+        };
+
+        // Spinning flat only sweeps X and Y
+        let mut calibrator = MagCalibrator::new();
+        assert_eq!(calibrator.coverage(), 0.0);
+        for azimuth in (0..360).step_by(15) {
+            calibrator.push(&point(0.0, azimuth as f64));
+        }
+        assert_eq!(calibrator.coverage(), 0.0);
+        assert_eq!(calibrator.finish(), ([0.0; 3], [1.0; 3]));
+
+        // Then through every orientation
+        for elevation in (-90..=90).step_by(15) {
+            for azimuth in (0..360).step_by(15) {
+                calibrator.push(&point(elevation as f64, azimuth as f64));
+            }
+        }
+        let (offsets, scales) = calibrator.finish();
+        assert_eq!(offsets, [100.0, -200.0, 50.0]);
+        assert_eq!(scales, [0.75, 1.0, 1.5]);
+        // Smallest semi-axis over the largest
+        assert_eq!(calibrator.coverage(), 0.5);
+    }
+
+    #[test]
+    fn temp_coefficient_scales_away_from_25_c() {
+        // x = 1 gauss at ±4 gauss; temperature 0 LSB = 25 C, 480 LSB = 85 C
+        let read = |temp: [u8; 2]| {
+            vec![
+                Transaction::write_read(LIS3MDL_ADDRESS, vec![STATUS_REG], vec![STATUS_ZYXDA]),
+                Transaction::write_read(
+                    LIS3MDL_ADDRESS,
+                    vec![OUT_X_L | ST_AUTO_INCREMENT],
+                    vec![0x00, 0x20, 0, 0, 0, 0, temp[0], temp[1]],
+                ),
+            ]
+        };
+        let mut transactions = init_transactions();
+        transactions.extend(read([0x00, 0x00]));
+        transactions.extend(read([0xE0, 0x01]));
+        transactions.extend(read([0xE0, 0x01]));
+        let mut i2c = I2cMock::new(&transactions);
+        let mut mag = Lis3mdl::new(i2c.clone());
+        mag.init().unwrap();
+        mag.set_temp_coefficient(1000.0);
+
+        assert_eq!(mag.read_magnetometer_gauss().unwrap(), (1.0, 0.0, 0.0));
+        let (x, _, _) = mag.read_magnetometer_gauss().unwrap();
+        assert!((x - 1.06).abs() < 1e-6);
+
+        // The default coefficient leaves a hot reading alone
+        mag.set_temp_coefficient(0.0);
+        assert_eq!(mag.read_magnetometer_gauss().unwrap(), (1.0, 0.0, 0.0));
+        i2c.done();
+    }
+
+    #[test]
+    fn dump_control_registers_decodes_the_fields() {
+        let mut i2c = I2cMock::new(&[
+            Transaction::write_read(LIS3MDL_ADDRESS, vec![CTRL_REG1 | ST_AUTO_INCREMENT], vec![0xF0, 0x20, 0x01, 0x0A, 0x40]),
+            Transaction::write_read(LIS3MDL_ADDRESS, vec![CTRL_REG1 | ST_AUTO_INCREMENT], vec![0x22, 0x00, 0x03, 0x04, 0x80]),
+        ]);
+        let mut mag = Lis3mdl::new(i2c.clone());
+
+        // Temperature on, ultra-high performance at 10 Hz, ±8 gauss, single
+        // conversion, Z in high performance, big-endian output, BDU
+        let regs = ControlRegs::from_bytes(mag.dump_control_registers().unwrap());
+        assert_eq!(regs.raw, [0xF0, 0x20, 0x01, 0x0A, 0x40]);
+        assert!(regs.temp_enabled && !regs.fast_odr && !regs.self_test);
+        assert!(matches!(regs.xy_mode, PerformanceMode::UltraHigh));
+        assert!(matches!(regs.data_rate, DataRate::Hz10));
+        assert!(matches!(regs.full_scale, FullScale::Gauss8));
+        assert_eq!(regs.operating_mode, OperatingMode::Single);
+        assert!(matches!(regs.z_mode, PerformanceMode::High));
+        assert!(regs.big_endian && regs.block_data_update && !regs.fast_read);
+
+        // FAST_ODR in medium performance: 560 Hz whatever DO says; MD 11 is power-down
+        let regs = ControlRegs::from_bytes(mag.dump_control_registers().unwrap());
+        assert!(regs.fast_odr && !regs.temp_enabled);
+        assert!(matches!(regs.xy_mode, PerformanceMode::Medium));
+        assert!(matches!(regs.data_rate, DataRate::Hz560));
+        assert!(matches!(regs.full_scale, FullScale::Gauss4));
+        assert_eq!(regs.operating_mode, OperatingMode::PowerDown);
+        assert!(matches!(regs.z_mode, PerformanceMode::Medium));
+        assert!(regs.fast_read && !regs.big_endian && !regs.block_data_update);
+        i2c.done();
+    }
+
+    #[test]
+    fn decimator_emits_the_mean_every_factor_samples() {
+        let mut decimator: Decimator<4> = Decimator::new();
+        let mut push = |s: MagnetometerData| decimator.push(s).map(|m| (m.x, m.y, m.z, m.temperature));
+        let block = [
+            MagnetometerData { x: 10, y: -10, z: 100, temperature: 40 },
+            MagnetometerData { x: 20, y: -20, z: 101, temperature: 40 },
+            MagnetometerData { x: 30, y: -30, z: 102, temperature: 48 },
+            MagnetometerData { x: 41, y: -41, z: 103, temperature: 48 },
+        ];
+
+        let out: Vec<_> = block.iter().chain(&block).map(|&s| push(s)).collect();
+        // Truncated toward zero: 101 / 4 and -101 / 4 both drop the fraction
+        let mean = Some((25, -25, 101, 44));
+        assert_eq!(out, [None, None, None, mean, None, None, None, mean]);
+
+        // Full-scale samples don't overflow the accumulator
+        let out: Vec<_> = (0..4).map(|_| push(sample(i16::MAX, i16::MIN, 0))).collect();
+        assert_eq!(out[3], Some((i16::MAX, i16::MIN, 0, 0)));
+
+        // reset drops a partial block
+        push(sample(1000, 0, 0));
+        decimator.reset();
+        let out: Vec<_> = block.iter().map(|&s| decimator.push(s).map(|m| m.x)).collect();
+        assert_eq!(out, [None, None, None, Some(25)]);
     }
 }
@@ -1,5 +1,75 @@
+// Driver-internal logging over RTT, compiled out without the `rtt-log` feature.
+// Arguments are still type-checked when disabled so nothing becomes unused.
+macro_rules! sensor_log {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "rtt-log")]
+        rtt_target::rprintln!($($arg)*);
+        #[cfg(not(feature = "rtt-log"))]
+        let _ = format_args!($($arg)*);
+    }};
+}
+
+pub mod fusion;
 pub mod gps;
+pub mod i2c;
 pub mod lis3mdl;
+pub mod sampler;
+pub mod spi;
+#[cfg(test)]
+mod test_vectors;
+pub mod units;
+
+pub use fusion::HeadingFuser;
+pub use gps::{GpsManager, NavId, UbxClass, UbxEvent};
+pub use lis3mdl::{Axis, Decimator, Lis3mdl, Lis3mdlSpi, MagCalibrator, MagFilter, MagnetometerData, SharedLis3mdl};
+pub use sampler::{SensorFrame, SensorSampler};
+pub use spi::SpiRegisters;
+pub use units::TypedFix;
+
+/// Register-level access to a sensor, independent of the bus it sits on
+///
+/// Errors come back already wrapped in the crate `Error` (`Error::I2c` or
+/// `Error::Spi`), so drivers can forward them with `?`.
+pub trait RegisterBus {
+    type Error;
+
+    /// Write a single register
+    fn write_reg(&mut self, register: u8, value: u8) -> Result<(), crate::Error<Self::Error>>;
+
+    /// Read a single register
+    fn read_reg(&mut self, register: u8) -> Result<u8, crate::Error<Self::Error>>;
+
+    /// Burst-read consecutive registers starting at `start_register`
+    fn read_regs(&mut self, start_register: u8, buffer: &mut [u8]) -> Result<(), crate::Error<Self::Error>>;
+}
+
+/// Blocking magnetometer interface so fusion code can be generic over the sensor
+///
+/// ```
+/// use marv::sensors::{Magnetometer, MagnetometerData};
+///
+/// fn sample_heading<M: Magnetometer>(mag: &mut M) -> Result<f32, M::Error> {
+///     let MagnetometerData { x, y, .. } = mag.read()?;
+///     Ok(libm::atan2f(y as f32, x as f32).to_degrees())
+/// }
+///
+/// // Any sensor works, here one that always reads the same field
+/// struct Fixed(MagnetometerData);
+///
+/// impl Magnetometer for Fixed {
+///     type Error = ();
+///
+///     fn read(&mut self) -> Result<MagnetometerData, ()> {
+///         Ok(self.0)
+///     }
+/// }
+///
+/// let mut mag = Fixed(MagnetometerData { x: 0, y: 1000, z: 0, temperature: 0 });
+/// assert_eq!(sample_heading(&mut mag), Ok(90.0));
+/// ```
+pub trait Magnetometer {
+    type Error;
 
-pub use gps::GpsManager;
-pub use lis3mdl::Lis3mdl;
+    /// Read one raw sample of the three axes
+    fn read(&mut self) -> Result<MagnetometerData, Self::Error>;
+}
@@ -0,0 +1,87 @@
+use super::gps::{GpsData, UbxEvent, UbxParser};
+use super::RegisterBus;
+use super::lis3mdl::{Lis3mdl, MagnetometerData};
+use crate::Error;
+
+// One logging cycle: latest GPS fix plus a fresh magnetometer reading
+#[derive(Clone, Copy)]
+pub struct SensorFrame {
+    pub timestamp_ms: u32,
+    pub gps: Option<GpsData>, // Most recent NAV-PVT, None until the first one arrives
+    pub mag: MagnetometerData,
+}
+
+// Fixed-rate sampler combining the GPS parser and the magnetometer
+pub struct SensorSampler<BUS> {
+    parser: UbxParser,
+    magnetometer: Lis3mdl<BUS>,
+    last_fix: Option<GpsData>,
+}
+
+impl<BUS: RegisterBus> SensorSampler<BUS> {
+    pub fn new(magnetometer: Lis3mdl<BUS>) -> Self {
+        Self {
+            parser: UbxParser::new(),
+            magnetometer,
+            last_fix: None,
+        }
+    }
+
+    /// Feed a received GPS byte; call for every byte between samples
+    pub fn process_gps_byte(&mut self, byte: u8) {
+        if let Some(UbxEvent::NavPvt(fix)) = self.parser.parse_byte(byte) {
+            self.last_fix = Some(fix);
+        }
+    }
+
+    /// Take one frame: reads the magnetometer now and attaches the latest fix
+    pub fn sample(&mut self, now_ms: u32) -> Result<SensorFrame, Error<BUS::Error>> {
+        let mag = self.magnetometer.read_magnetometer()?;
+
+        Ok(SensorFrame {
+            timestamp_ms: now_ms,
+            gps: self.last_fix,
+            mag,
+        })
+    }
+
+    /// Access the magnetometer driver directly (e.g. for init or calibration)
+    pub fn magnetometer(&mut self) -> &mut Lis3mdl<BUS> {
+        &mut self.magnetometer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensors::i2c::ST_AUTO_INCREMENT;
+    use crate::sensors::lis3mdl::{LIS3MDL_ADDRESS, OUT_X_L, STATUS_REG};
+    use crate::sensors::test_vectors::NAV_PVT;
+    use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction};
+
+    #[test]
+    fn sample_pairs_a_fresh_mag_read_with_the_latest_fix() {
+        let read = [
+            Transaction::write_read(LIS3MDL_ADDRESS, vec![STATUS_REG], vec![0x08]),
+            Transaction::write_read(LIS3MDL_ADDRESS, vec![OUT_X_L | ST_AUTO_INCREMENT], vec![1, 0, 2, 0, 3, 0, 0, 0]),
+        ];
+        let mut i2c = I2cMock::new(&[read.clone(), read].concat());
+        let mut sampler = SensorSampler::new(Lis3mdl::new(i2c.clone()));
+
+        // No NAV-PVT yet
+        let frame = sampler.sample(1_000).unwrap();
+        assert!(frame.gps.is_none());
+        assert_eq!((frame.timestamp_ms, frame.mag.x, frame.mag.y, frame.mag.z), (1_000, 1, 2, 3));
+
+        for &byte in &NAV_PVT {
+            sampler.process_gps_byte(byte);
+        }
+        let frame = sampler.sample(2_000).unwrap();
+        let Some(fix) = frame.gps else {
+            panic!("expected the fed NAV-PVT");
+        };
+        assert_eq!((fix.latitude, fix.longitude), (297_000_000, -975_000_000));
+        assert_eq!(frame.timestamp_ms, 2_000);
+        i2c.done();
+    }
+}
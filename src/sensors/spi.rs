@@ -0,0 +1,94 @@
+use embedded_hal::spi::{Operation, SpiDevice};
+
+use super::RegisterBus;
+use crate::Error;
+
+// ST sensors on SPI: bit 7 of the address byte selects a read, bit 6
+// auto-increments the address on multi-byte transfers
+pub const ST_SPI_READ: u8 = 0x80;
+pub const ST_SPI_AUTO_INCREMENT: u8 = 0x40;
+
+/// Register-level access to an ST sensor on 4-wire SPI
+///
+/// Chip select is handled by the `SpiDevice`, which asserts it for the
+/// duration of each transaction.
+pub struct SpiRegisters<SPI> {
+    spi: SPI,
+}
+
+impl<SPI: SpiDevice> SpiRegisters<SPI> {
+    pub fn new(spi: SPI) -> Self {
+        Self { spi }
+    }
+
+    /// Give back the underlying device
+    pub fn release(self) -> SPI {
+        self.spi
+    }
+}
+
+impl<SPI: SpiDevice> RegisterBus for SpiRegisters<SPI> {
+    type Error = SPI::Error;
+
+    fn write_reg(&mut self, register: u8, value: u8) -> Result<(), Error<SPI::Error>> {
+        self.spi.write(&[register & 0x3F, value]).map_err(Error::Spi)
+    }
+
+    fn read_reg(&mut self, register: u8) -> Result<u8, Error<SPI::Error>> {
+        let mut buffer = [0u8; 1];
+        self.read_regs(register, &mut buffer)?;
+        Ok(buffer[0])
+    }
+
+    fn read_regs(&mut self, start_register: u8, buffer: &mut [u8]) -> Result<(), Error<SPI::Error>> {
+        let mut address = (start_register & 0x3F) | ST_SPI_READ;
+        if buffer.len() > 1 {
+            address |= ST_SPI_AUTO_INCREMENT;
+        }
+        self.spi
+            .transaction(&mut [Operation::Write(&[address]), Operation::Read(buffer)])
+            .map_err(Error::Spi)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensors::lis3mdl::{CTRL_REG1, OUT_X_L, WHO_AM_I};
+    use embedded_hal_mock::eh1::spi::{Mock as SpiMock, Transaction};
+
+    #[test]
+    fn register_reads_set_the_read_and_auto_increment_bits() {
+        let mut spi = SpiMock::new(&[
+            Transaction::transaction_start(),
+            Transaction::write_vec(vec![WHO_AM_I | ST_SPI_READ]),
+            Transaction::read_vec(vec![0x3D]),
+            Transaction::transaction_end(),
+            Transaction::transaction_start(),
+            Transaction::write_vec(vec![OUT_X_L | ST_SPI_READ | ST_SPI_AUTO_INCREMENT]),
+            Transaction::read_vec(vec![0x10, 0x00, 0x20, 0x00]),
+            Transaction::transaction_end(),
+        ]);
+        let mut regs = SpiRegisters::new(spi.clone());
+
+        assert_eq!(regs.read_reg(WHO_AM_I), Ok(0x3D));
+        let mut buffer = [0u8; 4];
+        regs.read_regs(OUT_X_L, &mut buffer).unwrap();
+        assert_eq!(buffer, [0x10, 0x00, 0x20, 0x00]);
+        spi.done();
+    }
+
+    #[test]
+    fn register_writes_clear_the_control_bits() {
+        let mut spi = SpiMock::new(&[
+            Transaction::transaction_start(),
+            Transaction::write_vec(vec![CTRL_REG1, 0x70]),
+            Transaction::transaction_end(),
+        ]);
+        let mut regs = SpiRegisters::new(spi.clone());
+
+        // Bits 7 and 6 never reach the address byte on a write
+        regs.write_reg(CTRL_REG1 | ST_SPI_READ | ST_SPI_AUTO_INCREMENT, 0x70).unwrap();
+        spi.done();
+    }
+}
@@ -0,0 +1,89 @@
+// Shared UBX/NMEA byte streams for parser tests
+//
+// Synthetic, not captured from a receiver: the frames were built by hand to
+// the NEO-M9N layout (protocol 32, 92-byte NAV-PVT) with real checksums, and
+// the NMEA lines follow the receiver's format. Plain const arrays, so they
+// work in no_std code too.
+
+// NAV-PVT: 2024-06-01 12:30:45 UTC, 3D fix with 12 SVs at 29.7 N 97.5 W,
+// 205 m MSL, hAcc 1.5 m, gSpeed 0.126 m/s heading 340 deg
+pub(crate) const NAV_PVT: [u8; 100] = [
+    0xB5, 0x62, 0x01, 0x07, 0x5C, 0x00, 0x00, 0x70, 0x99, 0x14, 0xE8, 0x07, 0x06, 0x01, 0x0C, 0x1E,
+    0x2D, 0x37, 0x19, 0x00, 0x00, 0x00, 0x40, 0xE2, 0x01, 0x00, 0x03, 0x01, 0xEA, 0x0C, 0x40, 0xAE,
+    0xE2, 0xC5, 0x40, 0xDC, 0xB3, 0x11, 0x20, 0xBF, 0x02, 0x00, 0xC8, 0x20, 0x03, 0x00, 0xDC, 0x05,
+    0x00, 0x00, 0xC4, 0x09, 0x00, 0x00, 0x78, 0x00, 0x00, 0x00, 0xD8, 0xFF, 0xFF, 0xFF, 0x05, 0x00,
+    0x00, 0x00, 0x7E, 0x00, 0x00, 0x00, 0x80, 0xCC, 0x06, 0x02, 0xFA, 0x00, 0x00, 0x00, 0xF0, 0x49,
+    0x02, 0x00, 0x78, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0xFB, 0x8D,
+];
+
+// ACK-ACK for a CFG-PRT command
+pub(crate) const ACK_ACK_CFG_PRT: [u8; 10] = [0xB5, 0x62, 0x05, 0x01, 0x02, 0x00, 0x06, 0x00, 0x0E, 0x37];
+
+// NAV_PVT with bit 2 of the latitude's low byte flipped in transit; the
+// checksum no longer matches, so the parser must drop it and resync
+pub(crate) const NAV_PVT_CORRUPTED: [u8; 100] = [
+    0xB5, 0x62, 0x01, 0x07, 0x5C, 0x00, 0x00, 0x70, 0x99, 0x14, 0xE8, 0x07, 0x06, 0x01, 0x0C, 0x1E,
+    0x2D, 0x37, 0x19, 0x00, 0x00, 0x00, 0x40, 0xE2, 0x01, 0x00, 0x03, 0x01, 0xEA, 0x0C, 0x40, 0xAE,
+    0xE2, 0xC5, 0x44, 0xDC, 0xB3, 0x11, 0x20, 0xBF, 0x02, 0x00, 0xC8, 0x20, 0x03, 0x00, 0xDC, 0x05,
+    0x00, 0x00, 0xC4, 0x09, 0x00, 0x00, 0x78, 0x00, 0x00, 0x00, 0xD8, 0xFF, 0xFF, 0xFF, 0x05, 0x00,
+    0x00, 0x00, 0x7E, 0x00, 0x00, 0x00, 0x80, 0xCC, 0x06, 0x02, 0xFA, 0x00, 0x00, 0x00, 0xF0, 0x49,
+    0x02, 0x00, 0x78, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0xFB, 0x8D,
+];
+
+// NMEA sentences the receiver interleaves with UBX until they are disabled
+pub(crate) const NMEA_NOISE: &[u8] = b"$GNGGA,123045.00,2942.00000,N,09730.00000,W,1,12,0.80,205.0,M,-25.0,M,,*77\r\n\
+$GNRMC,123045.00,A,2942.00000,N,09730.00000,W,0.245,340.00,010624,,,A,V*1C\r\n";
+
+// One second of output: NMEA, a fix, an ACK, a corrupted fix and a good one
+pub(crate) const MIXED_STREAM: [&[u8]; 5] = [NMEA_NOISE, &NAV_PVT, &ACK_ACK_CFG_PRT, &NAV_PVT_CORRUPTED, &NAV_PVT];
+
+// Only compiled for tests (see sensors/mod.rs)
+mod tests {
+    use super::*;
+    use crate::sensors::gps::UbxParser;
+    use crate::sensors::UbxEvent;
+
+    #[test]
+    fn nav_pvt_vector_decodes() {
+        let mut parser: UbxParser = UbxParser::new();
+        let Some(UbxEvent::NavPvt(fix)) = NAV_PVT.iter().filter_map(|&b| parser.parse_byte(b)).last() else {
+            panic!("expected a NavPvt event");
+        };
+        assert!(fix.valid);
+        assert_eq!((fix.latitude, fix.longitude, fix.height_msl), (297_000_000, -975_000_000, 205_000));
+        assert_eq!((fix.year, fix.month, fix.day, fix.satellites), (2024, 6, 1, 12));
+        assert_eq!(fix.heading_motion, 34_000_000);
+    }
+
+    #[test]
+    fn bench_mixed_stream() {
+        const SECONDS: usize = 10_000;
+
+        let mut stream = Vec::new();
+        for _ in 0..SECONDS {
+            for chunk in MIXED_STREAM {
+                stream.extend_from_slice(chunk);
+            }
+        }
+
+        let mut parser: UbxParser = UbxParser::new();
+        let (mut fixes, mut acks, mut other) = (0, 0, 0);
+        for &byte in &stream {
+            match parser.parse_byte(byte) {
+                Some(UbxEvent::NavPvt(_)) => fixes += 1,
+                Some(UbxEvent::Ack { class: 0x06, id: 0x00 }) => acks += 1,
+                Some(_) => other += 1,
+                None => {}
+            }
+        }
+
+        assert_eq!((fixes, acks, other), (2 * SECONDS, SECONDS, 0));
+        let stats = parser.stats();
+        assert_eq!(stats.frames as usize, 3 * SECONDS);
+        assert_eq!(stats.checksum_errors as usize, SECONDS);
+        // The NMEA text, plus the corrupted frame's CK_B where the resync happens
+        assert_eq!(stats.bytes_skipped as usize, SECONDS * (NMEA_NOISE.len() + 1));
+    }
+}
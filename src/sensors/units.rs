@@ -0,0 +1,113 @@
+use super::gps::GpsData;
+
+// Unit-carrying newtypes over the raw GpsData integers. The plain fields
+// stay as they are for code that doesn't want the ceremony
+
+/// Length in millimeters (GpsData heights and accuracies)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Millimeters(pub i32);
+
+impl Millimeters {
+    pub fn into_meters(self) -> f64 {
+        self.0 as f64 / 1000.0
+    }
+}
+
+/// Velocity in millimeters per second (GpsData speeds and NED velocities)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MillimetersPerSecond(pub i32);
+
+impl MillimetersPerSecond {
+    pub fn into_meters_per_second(self) -> f64 {
+        self.0 as f64 / 1000.0
+    }
+}
+
+/// Latitude in 1e-7 degrees, positive north
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Latitude(pub i32);
+
+impl Latitude {
+    pub fn into_degrees(self) -> f64 {
+        self.0 as f64 / 1e7
+    }
+}
+
+/// Longitude in 1e-7 degrees, positive east
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Longitude(pub i32);
+
+impl Longitude {
+    pub fn into_degrees(self) -> f64 {
+        self.0 as f64 / 1e7
+    }
+}
+
+/// Height of a fix, above mean sea level or the ellipsoid depending on the field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Altitude(pub Millimeters);
+
+impl Altitude {
+    pub fn into_meters(self) -> f64 {
+        self.0.into_meters()
+    }
+}
+
+/// Ground speed (always non-negative)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Speed(pub MillimetersPerSecond);
+
+impl Speed {
+    pub fn into_meters_per_second(self) -> f64 {
+        self.0.into_meters_per_second()
+    }
+}
+
+/// Position and motion of a GpsData, with units in the types
+///
+/// Mixing up units no longer compiles:
+///
+/// ```compile_fail,E0308
+/// use marv::sensors::gps::GpsData;
+/// use marv::sensors::units::Speed;
+///
+/// fn climb_rate(speed: Speed) -> f64 { speed.into_meters_per_second() }
+/// let fix = GpsData::new().typed();
+/// climb_rate(fix.altitude_msl); // expected `Speed`, found `Altitude`
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TypedFix {
+    pub valid: bool,
+    pub latitude: Latitude,
+    pub longitude: Longitude,
+    pub altitude_msl: Altitude,
+    pub altitude_ellipsoid: Altitude,
+    pub ground_speed: Speed,
+    pub velocity_north: MillimetersPerSecond,
+    pub velocity_east: MillimetersPerSecond,
+    pub velocity_down: MillimetersPerSecond,
+}
+
+impl GpsData {
+    /// View the position and motion fields through the unit newtypes
+    pub fn typed(&self) -> TypedFix {
+        TypedFix {
+            valid: self.valid,
+            latitude: Latitude(self.latitude),
+            longitude: Longitude(self.longitude),
+            altitude_msl: Altitude(Millimeters(self.height_msl)),
+            altitude_ellipsoid: Altitude(Millimeters(self.height_ellipsoid_mm)),
+            ground_speed: Speed(MillimetersPerSecond(self.ground_speed)),
+            velocity_north: MillimetersPerSecond(self.velocity_north),
+            velocity_east: MillimetersPerSecond(self.velocity_east),
+            velocity_down: MillimetersPerSecond(self.velocity_down),
+        }
+    }
+}
@@ -0,0 +1,173 @@
+// Wall-clock timing helpers keyed off a millisecond counter (Hardware::millis
+// on the board), free of the HAL so they can be tested on the host
+
+// Default heartbeat toggle interval
+pub const HEARTBEAT_INTERVAL_MS: u32 = 500;
+
+/// Fixed wall-clock interval timer keyed off `Hardware::millis`
+///
+/// `tick` returns true once per elapsed interval regardless of how often it
+/// is called, so a blink driven by it doesn't depend on loop throughput or
+/// the clock configuration. The first tick fires immediately. If the caller
+/// stalls for several intervals only one tick fires and the schedule
+/// restarts from then, rather than firing a burst to catch up.
+pub struct Heartbeat {
+    interval_ms: u32,
+    last_ms: Option<u32>,
+}
+
+impl Heartbeat {
+    pub fn new(interval_ms: u32) -> Self {
+        Self {
+            interval_ms,
+            last_ms: None,
+        }
+    }
+
+    /// Change the interval; the next tick fires immediately
+    pub fn set_interval(&mut self, interval_ms: u32) {
+        self.interval_ms = interval_ms;
+        self.last_ms = None;
+    }
+
+    /// Returns true when the interval has elapsed since the last firing
+    pub fn tick(&mut self, now_ms: u32) -> bool {
+        let Some(last) = self.last_ms else {
+            self.last_ms = Some(now_ms);
+            return true;
+        };
+
+        let elapsed = now_ms.wrapping_sub(last);
+        if elapsed < self.interval_ms {
+            return false;
+        }
+
+        self.last_ms = if elapsed < 2 * self.interval_ms {
+            Some(last.wrapping_add(self.interval_ms))
+        } else {
+            Some(now_ms)
+        };
+        true
+    }
+}
+
+// Handle of a task registered with a Scheduler
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaskId(pub usize);
+
+/// Periodic tasks keyed off `Hardware::millis`, up to N of them
+///
+/// Each task is a `Heartbeat`, so tasks fire once per interval however often
+/// `run` is called, all of them on the first `run`, and a stall skips missed
+/// intervals instead of bursting. Due tasks are reported in the order they
+/// were added.
+pub struct Scheduler<const N: usize> {
+    tasks: [Option<Heartbeat>; N],
+}
+
+impl<const N: usize> Scheduler<N> {
+    pub fn new() -> Self {
+        Self {
+            tasks: core::array::from_fn(|_| None),
+        }
+    }
+
+    /// Register a task firing every `interval_ms`
+    /// Returns None if all N slots are taken
+    pub fn add(&mut self, interval_ms: u32) -> Option<TaskId> {
+        let index = self.tasks.iter().position(Option::is_none)?;
+        self.tasks[index] = Some(Heartbeat::new(interval_ms));
+        Some(TaskId(index))
+    }
+
+    /// Stop a task; its TaskId may be handed out again by `add`
+    pub fn remove(&mut self, id: TaskId) {
+        if let Some(slot) = self.tasks.get_mut(id.0) {
+            *slot = None;
+        }
+    }
+
+    /// Call `on_due` for every task whose interval has elapsed
+    pub fn run(&mut self, now_ms: u32, on_due: &mut impl FnMut(TaskId)) {
+        for (index, slot) in self.tasks.iter_mut().enumerate() {
+            if slot.as_mut().is_some_and(|task| task.tick(now_ms)) {
+                on_due(TaskId(index));
+            }
+        }
+    }
+}
+
+impl<const N: usize> Default for Scheduler<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heartbeat_fires_once_per_interval() {
+        let mut heartbeat = Heartbeat::new(HEARTBEAT_INTERVAL_MS);
+        let fired: Vec<u32> = [0, 1, 250, 499, 500, 501, 999, 1003, 1500]
+            .into_iter()
+            .filter(|&now| heartbeat.tick(now))
+            .collect();
+        // A late call doesn't shift the schedule: 1003 fires, 1500 still does
+        assert_eq!(fired, [0, 500, 1003, 1500]);
+    }
+
+    #[test]
+    fn heartbeat_skips_a_stall_and_survives_wraparound() {
+        let mut heartbeat = Heartbeat::new(500);
+        assert!(heartbeat.tick(0));
+        // Stalled for four intervals: one tick, then restart from there
+        assert!(heartbeat.tick(2_100));
+        assert!(!heartbeat.tick(2_500));
+        assert!(heartbeat.tick(2_600));
+
+        let mut heartbeat = Heartbeat::new(500);
+        assert!(heartbeat.tick(u32::MAX - 100));
+        assert!(!heartbeat.tick(u32::MAX));
+        assert!(heartbeat.tick(399));
+    }
+
+    #[test]
+    fn scheduler_runs_due_tasks_in_order() {
+        let mut scheduler: Scheduler<2> = Scheduler::new();
+        let fast = scheduler.add(100).unwrap();
+        let slow = scheduler.add(250).unwrap();
+        assert_eq!(scheduler.add(10), None);
+
+        let mut due = Vec::new();
+        for now in [0, 100, 200, 250] {
+            scheduler.run(now, &mut |id| due.push((now, id)));
+        }
+        assert_eq!(due, [(0, fast), (0, slow), (100, fast), (200, fast), (250, slow)]);
+
+        // A removed slot is reused
+        scheduler.remove(fast);
+        assert_eq!(scheduler.add(50), Some(fast));
+    }
+
+    #[test]
+    fn scheduler_fires_on_interval_boundaries_only() {
+        let mut scheduler: Scheduler<4> = Scheduler::new();
+        let mag = scheduler.add(12).unwrap(); // ~80 Hz
+        let gps = scheduler.add(100).unwrap();
+
+        // Polled every millisecond for a second
+        let (mut mag_at, mut gps_at) = (Vec::new(), Vec::new());
+        for now in 0..=1000 {
+            scheduler.run(now, &mut |id| match id {
+                id if id == mag => mag_at.push(now),
+                id if id == gps => gps_at.push(now),
+                _ => panic!("unknown task"),
+            });
+        }
+
+        assert_eq!(mag_at, (0..=1000).step_by(12).collect::<Vec<_>>());
+        assert_eq!(gps_at, (0..=1000).step_by(100).collect::<Vec<_>>());
+    }
+}